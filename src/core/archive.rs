@@ -0,0 +1,197 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  archive.rs                                           TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 14 2025 10:30 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 14 2025 10:30 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::downloader::Platform;
+use crate::core::error::{DownloadError, Result};
+use crate::core::outcome::DownloadOutcome;
+use crate::core::settings::{data_dir, write_atomically};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ARCHIVE_FILE: &str = "archive.json";
+
+/// Serialises read-modify-write cycles of downloads finishing at the same time.
+static ARCHIVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A video recorded as downloaded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveEntry {
+    pub platform: Platform,
+    pub id: String,
+    pub title: String,
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch
+    pub downloaded_at: u64,
+}
+
+/// Videos already downloaded, keyed by platform and video id.
+///
+/// Works like yt-dlp's `--download-archive`, but lives in our data directory
+/// and remembers where each video was saved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DownloadArchive {
+    entries: HashMap<String, ArchiveEntry>,
+}
+
+impl DownloadArchive {
+    /// Loads the archive, starting empty when it is missing or unreadable.
+    pub fn load() -> Self {
+        Self::load_from(&archive_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable archive {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| DownloadError::IoError(format!("Failed to encode archive: {}", e)))?;
+        write_atomically(path, contents.as_bytes())
+    }
+
+    pub fn get(&self, platform: &Platform, id: &str) -> Option<&ArchiveEntry> {
+        self.entries.get(&key(platform, id))
+    }
+
+    /// Like `get`, but only while the recorded file still exists.
+    ///
+    /// A video whose file was deleted or moved away is downloaded again.
+    pub fn get_on_disk(&self, platform: &Platform, id: &str) -> Option<&ArchiveEntry> {
+        self.get(platform, id).filter(|entry| {
+            let present = entry.path.exists();
+            if !present {
+                info!(
+                    "{} is archived but {} is gone",
+                    entry.title,
+                    entry.path.display()
+                );
+            }
+            present
+        })
+    }
+
+    /// Records a finished download; outcomes without a video id are ignored.
+    pub fn add(&mut self, outcome: &DownloadOutcome) -> bool {
+        let Some(id) = outcome.video_id.as_deref().filter(|id| !id.is_empty()) else {
+            return false;
+        };
+
+        let downloaded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        self.entries.insert(
+            key(&outcome.platform, id),
+            ArchiveEntry {
+                platform: outcome.platform.clone(),
+                id: id.to_string(),
+                title: outcome.title.clone(),
+                path: outcome.primary_file.clone(),
+                downloaded_at,
+            },
+        );
+        true
+    }
+
+    /// Adds a finished download to the archive on disk.
+    pub fn record(outcome: &DownloadOutcome) -> Result<()> {
+        Self::record_in(&archive_path(), outcome)
+    }
+
+    pub fn record_in(path: &Path, outcome: &DownloadOutcome) -> Result<()> {
+        let _guard = ARCHIVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut archive = Self::load_from(path);
+        if archive.add(outcome) {
+            archive.save_to(path)?;
+            info!("Archived {} as downloaded", outcome.title);
+        }
+        Ok(())
+    }
+}
+
+fn key(platform: &Platform, id: &str) -> String {
+    format!("{:?} {}", platform, id)
+}
+
+fn archive_path() -> PathBuf {
+    data_dir().join(ARCHIVE_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_lookup() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_archive_{}", std::process::id()));
+        let path = dir.join(ARCHIVE_FILE);
+
+        let outcome = DownloadOutcome {
+            primary_file: PathBuf::from("/videos/Talk.mkv"),
+            title: "Talk".to_string(),
+            video_id: Some("abc123".to_string()),
+            platform: Platform::YouTube,
+            ..Default::default()
+        };
+        DownloadArchive::record_in(&path, &outcome).unwrap();
+        DownloadArchive::record_in(
+            &path,
+            &DownloadOutcome {
+                title: "No id".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let archive = DownloadArchive::load_from(&path);
+        assert_eq!(archive.entries.len(), 1);
+        // The recorded file was never written
+        assert!(archive.get_on_disk(&Platform::YouTube, "abc123").is_none());
+        assert!(archive.get(&Platform::YouTube, "abc123").is_some());
+        assert!(archive.get(&Platform::Rutube, "abc123").is_none());
+        assert_eq!(
+            archive.get(&Platform::YouTube, "abc123").unwrap().path,
+            PathBuf::from("/videos/Talk.mkv")
+        );
+
+        let kept = dir.join("Kept.mkv");
+        std::fs::write(&kept, b"video").unwrap();
+        DownloadArchive::record_in(
+            &path,
+            &DownloadOutcome {
+                primary_file: kept,
+                video_id: Some("def456".to_string()),
+                platform: Platform::YouTube,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let archive = DownloadArchive::load_from(&path);
+        assert!(archive.get_on_disk(&Platform::YouTube, "def456").is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
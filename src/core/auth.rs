@@ -0,0 +1,450 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  auth.rs                                              TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 15 2025 15:40 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 15 2025 15:40 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::downloader::Platform;
+use crate::core::error::{DownloadError, Result};
+use crate::core::settings::{config_dir, write_atomically};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUTH_FILE: &str = "auth.json";
+const COOKIES_DIR: &str = "cookies";
+
+/// Platforms whose videos can need a signed-in session.
+pub const PLATFORMS: [Platform; 8] = [
+    Platform::YouTube,
+    Platform::TikTok,
+    Platform::Twitter,
+    Platform::Instagram,
+    Platform::Reddit,
+    Platform::Vk,
+    Platform::Rutube,
+    Platform::Dzen,
+];
+
+/// Browser name for yt-dlp and a directory that appears in its profile paths.
+const BROWSERS: [(&str, &str); 8] = [
+    ("firefox", "mozilla"),
+    ("brave", "bravesoftware"),
+    ("edge", "microsoft-edge"),
+    ("vivaldi", "vivaldi"),
+    ("opera", "opera"),
+    ("chromium", "chromium"),
+    ("chrome", "google-chrome"),
+    ("chrome", "chrome"),
+];
+
+/// Where the cookies for a platform come from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuthSource {
+    /// A cookies.txt file imported into our config directory
+    CookiesFile(PathBuf),
+    /// Cookies read by yt-dlp from a browser profile on each run
+    Browser { browser: String, profile: PathBuf },
+}
+
+/// Whether a platform's downloads run signed in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthStatus {
+    SignedOut,
+    /// Number of cookies for the platform that have not expired
+    Cookies(usize),
+    Browser(String),
+    Expired,
+    Missing(PathBuf),
+}
+
+impl AuthStatus {
+    pub fn is_authenticated(&self) -> bool {
+        matches!(self, AuthStatus::Cookies(_) | AuthStatus::Browser(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            AuthStatus::SignedOut => "Not signed in".to_string(),
+            AuthStatus::Cookies(count) => format!("Signed in ({} cookies)", count),
+            AuthStatus::Browser(browser) => format!("Signed in via {} profile", browser),
+            AuthStatus::Expired => "Cookies expired - import fresh ones".to_string(),
+            AuthStatus::Missing(path) => format!("{} is missing", path.display()),
+        }
+    }
+}
+
+/// Cookie sources per platform, applied to every yt-dlp run for that platform.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct AuthProfiles {
+    profiles: HashMap<Platform, AuthSource>,
+}
+
+impl AuthProfiles {
+    /// Loads the profiles, starting signed out when the file is missing or unreadable.
+    pub fn load() -> Self {
+        Self::load_from(&config_dir().join(AUTH_FILE))
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!(
+                    "Ignoring unreadable auth profiles {}: {}",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&config_dir().join(AUTH_FILE))
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| {
+            DownloadError::IoError(format!("Failed to encode auth profiles: {}", e))
+        })?;
+        write_atomically(path, contents.as_bytes())
+    }
+
+    pub fn status(&self, platform: &Platform) -> AuthStatus {
+        match self.profiles.get(platform) {
+            None => AuthStatus::SignedOut,
+            Some(AuthSource::CookiesFile(path)) => match std::fs::read_to_string(path) {
+                Ok(contents) => match parse_cookies(&contents) {
+                    Ok(cookies) => match live_cookies(&cookies, platform, now()) {
+                        0 => AuthStatus::Expired,
+                        count => AuthStatus::Cookies(count),
+                    },
+                    Err(_) => AuthStatus::Expired,
+                },
+                Err(_) => AuthStatus::Missing(path.clone()),
+            },
+            Some(AuthSource::Browser { browser, profile }) => {
+                if profile.is_dir() {
+                    AuthStatus::Browser(browser.clone())
+                } else {
+                    AuthStatus::Missing(profile.clone())
+                }
+            }
+        }
+    }
+
+    /// Copies a Netscape cookies.txt file into the config directory for `platform`.
+    pub fn import_cookies(&mut self, platform: &Platform, source: &Path) -> Result<AuthStatus> {
+        self.import_cookies_into(&config_dir().join(COOKIES_DIR), platform, source)
+    }
+
+    pub fn import_cookies_into(
+        &mut self,
+        dir: &Path,
+        platform: &Platform,
+        source: &Path,
+    ) -> Result<AuthStatus> {
+        let contents = std::fs::read_to_string(source).map_err(|e| {
+            DownloadError::InvalidCookies(format!("cannot read {}: {}", source.display(), e))
+        })?;
+        let cookies = parse_cookies(&contents)?;
+        if !cookies.iter().any(|c| c.matches(platform)) {
+            return Err(DownloadError::InvalidCookies(format!(
+                "no cookies for {}",
+                domains(platform).join(" or ")
+            )));
+        }
+
+        let target = dir.join(format!("{:?}.txt", platform).to_lowercase());
+        write_private(&target, contents.as_bytes())?;
+        info!(
+            "Imported cookies for {:?} into {}",
+            platform,
+            target.display()
+        );
+
+        self.profiles
+            .insert(platform.clone(), AuthSource::CookiesFile(target));
+        Ok(self.status(platform))
+    }
+
+    /// Reads cookies from a browser profile directory on every run.
+    pub fn use_browser(&mut self, platform: &Platform, profile: &Path) -> Result<AuthStatus> {
+        let browser = browser_for_profile(profile).ok_or_else(|| {
+            DownloadError::InvalidCookies(format!(
+                "{} does not look like a Firefox or Chromium-based browser profile",
+                profile.display()
+            ))
+        })?;
+
+        self.forget_file(platform);
+        self.profiles.insert(
+            platform.clone(),
+            AuthSource::Browser {
+                browser: browser.to_string(),
+                profile: profile.to_path_buf(),
+            },
+        );
+        Ok(self.status(platform))
+    }
+
+    /// Signs `platform` out, deleting its imported cookies.
+    pub fn sign_out(&mut self, platform: &Platform) {
+        self.forget_file(platform);
+        self.profiles.remove(platform);
+    }
+
+    fn forget_file(&mut self, platform: &Platform) {
+        if let Some(AuthSource::CookiesFile(path)) = self.profiles.get(platform) {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// yt-dlp options that hand it the platform's cookies.
+    pub fn yt_dlp_args(&self, platform: &Platform) -> Vec<String> {
+        match self.profiles.get(platform) {
+            None => Vec::new(),
+            Some(AuthSource::CookiesFile(path)) if !path.is_file() => {
+                warn!("Cookies for {:?} are missing: {}", platform, path.display());
+                Vec::new()
+            }
+            Some(AuthSource::CookiesFile(path)) => {
+                vec!["--cookies".to_string(), path.display().to_string()]
+            }
+            Some(AuthSource::Browser { browser, profile }) => vec![
+                "--cookies-from-browser".to_string(),
+                format!("{}:{}", browser, profile.display()),
+            ],
+        }
+    }
+}
+
+/// Cookie options for a yt-dlp run against `platform`.
+pub fn yt_dlp_args(platform: &Platform) -> Vec<String> {
+    AuthProfiles::load().yt_dlp_args(platform)
+}
+
+/// Domains whose cookies sign a user in to `platform`.
+fn domains(platform: &Platform) -> &'static [&'static str] {
+    match platform {
+        Platform::YouTube => &["youtube.com", "google.com"],
+        Platform::TikTok => &["tiktok.com"],
+        Platform::Twitter => &["twitter.com", "x.com"],
+        Platform::Instagram => &["instagram.com"],
+        Platform::Reddit => &["reddit.com"],
+        Platform::Vk => &["vk.com", "vkvideo.ru"],
+        Platform::Rutube => &["rutube.ru"],
+        Platform::Dzen => &["dzen.ru", "yandex.ru"],
+        Platform::Other => &[],
+    }
+}
+
+/// The yt-dlp browser name for a profile directory, judged by its path.
+fn browser_for_profile(profile: &Path) -> Option<&'static str> {
+    let path = profile.to_string_lossy().to_lowercase();
+    BROWSERS
+        .iter()
+        .find(|(_, marker)| path.contains(marker))
+        .map(|(browser, _)| *browser)
+}
+
+/// One line of a cookies.txt file, as far as we need it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cookie {
+    domain: String,
+    /// Seconds since the Unix epoch; zero for session cookies
+    expires: u64,
+}
+
+impl Cookie {
+    fn matches(&self, platform: &Platform) -> bool {
+        let domain = self.domain.trim_start_matches('.');
+        domains(platform)
+            .iter()
+            .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+    }
+}
+
+/// Reads the Netscape format browsers' cookie exporters and yt-dlp write.
+fn parse_cookies(contents: &str) -> Result<Vec<Cookie>> {
+    let mut cookies = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        // HttpOnly cookies are written as comments with this prefix
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(DownloadError::InvalidCookies(format!(
+                "line {} is not a Netscape cookie",
+                number + 1
+            )));
+        }
+
+        cookies.push(Cookie {
+            domain: fields[0].to_string(),
+            expires: fields[4].parse().unwrap_or_default(),
+        });
+    }
+
+    if cookies.is_empty() {
+        return Err(DownloadError::InvalidCookies(
+            "the file contains no cookies".to_string(),
+        ));
+    }
+    Ok(cookies)
+}
+
+fn live_cookies(cookies: &[Cookie], platform: &Platform, now: u64) -> usize {
+    cookies
+        .iter()
+        .filter(|c| c.matches(platform) && (c.expires == 0 || c.expires > now))
+        .count()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Like `write_atomically`, but readable by the current user only.
+///
+/// The directory is closed to others first, so the file is never exposed
+/// before its own permissions are tightened.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    let protect = |target: &Path, mode: u32| {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(target, std::fs::Permissions::from_mode(mode)).map_err(|e| {
+            DownloadError::IoError(format!("Failed to protect {}: {}", target.display(), e))
+        })
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            DownloadError::IoError(format!("Failed to create {}: {}", parent.display(), e))
+        })?;
+        #[cfg(unix)]
+        protect(parent, 0o700)?;
+    }
+
+    write_atomically(path, contents)?;
+    #[cfg(unix)]
+    protect(path, 0o600)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOKIES: &str = "# Netscape HTTP Cookie File\n\
+        .vk.com\tTRUE\t/\tTRUE\t4102444800\tremixsid\tabc\n\
+        #HttpOnly_.vk.com\tTRUE\t/\tTRUE\t0\tremixlang\t3\n\
+        .vk.com\tTRUE\t/\tFALSE\t1000\told\tx\n\
+        .example.com\tTRUE\t/\tFALSE\t0\tother\ty\n";
+
+    #[test]
+    fn test_parse_cookies() {
+        let cookies = parse_cookies(COOKIES).unwrap();
+        assert_eq!(cookies.len(), 4);
+        assert_eq!(live_cookies(&cookies, &Platform::Vk, 2000), 2);
+        assert_eq!(live_cookies(&cookies, &Platform::YouTube, 2000), 0);
+
+        assert!(parse_cookies("# Netscape HTTP Cookie File\n").is_err());
+        assert!(parse_cookies("{\"name\": \"remixsid\"}").is_err());
+    }
+
+    #[test]
+    fn test_import_and_args() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_auth_{}", std::process::id()));
+        let source = dir.join("export.txt");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&source, COOKIES).unwrap();
+
+        let mut profiles = AuthProfiles::default();
+        let cookies_dir = dir.join(COOKIES_DIR);
+        assert!(profiles
+            .import_cookies_into(&cookies_dir, &Platform::Instagram, &source)
+            .is_err());
+        assert_eq!(
+            profiles
+                .import_cookies_into(&cookies_dir, &Platform::Vk, &source)
+                .unwrap(),
+            AuthStatus::Cookies(2)
+        );
+
+        let copy = cookies_dir.join("vk.txt");
+        assert_eq!(
+            profiles.yt_dlp_args(&Platform::Vk),
+            vec!["--cookies".to_string(), copy.display().to_string()]
+        );
+        assert!(profiles.yt_dlp_args(&Platform::YouTube).is_empty());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&copy).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        profiles.sign_out(&Platform::Vk);
+        assert!(!copy.exists());
+        assert_eq!(profiles.status(&Platform::Vk), AuthStatus::SignedOut);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_browser_profiles() {
+        assert_eq!(
+            browser_for_profile(Path::new("/home/u/.mozilla/firefox/abcd.default-release")),
+            Some("firefox")
+        );
+        assert_eq!(
+            browser_for_profile(Path::new("/home/u/.config/google-chrome/Default")),
+            Some("chrome")
+        );
+        assert_eq!(
+            browser_for_profile(Path::new(
+                "/home/u/.config/BraveSoftware/Brave-Browser/Default"
+            )),
+            Some("brave")
+        );
+        assert_eq!(browser_for_profile(Path::new("/tmp/profile")), None);
+
+        let mut profiles = AuthProfiles::default();
+        profiles
+            .use_browser(
+                &Platform::YouTube,
+                Path::new("/nonexistent/.mozilla/firefox/x"),
+            )
+            .unwrap();
+        assert_eq!(
+            profiles.yt_dlp_args(&Platform::YouTube),
+            vec![
+                "--cookies-from-browser",
+                "firefox:/nonexistent/.mozilla/firefox/x"
+            ]
+        );
+        assert!(!profiles.status(&Platform::YouTube).is_authenticated());
+    }
+}
@@ -0,0 +1,195 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  bandwidth.rs                                         TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 14 2025 16:45 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 14 2025 16:45 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use crate::core::progress::format_bytes;
+use log::info;
+use std::sync::Mutex;
+
+/// Lowest share handed to a download, so a crowded cap never stalls one completely.
+const MIN_RATE: u64 = 16 * 1024;
+
+/// The global cap and the downloads currently sharing it.
+struct Limiter {
+    global: Option<u64>,
+    /// Slot id and the download's own cap
+    slots: Vec<(u64, Option<u64>)>,
+    next_id: u64,
+}
+
+static LIMITER: Mutex<Limiter> = Mutex::new(Limiter {
+    global: None,
+    slots: Vec::new(),
+    next_id: 0,
+});
+
+fn limiter() -> std::sync::MutexGuard<'static, Limiter> {
+    LIMITER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Changes the cap shared by all downloads, in bytes per second.
+///
+/// Running downloads notice the new share on their next progress update and
+/// restart yt-dlp with it, resuming their partial files.
+pub fn set_global_limit(limit: Option<u64>) {
+    let mut limiter = limiter();
+    if limiter.global != limit {
+        info!(
+            "Global speed limit: {}",
+            limit.map(format_rate).unwrap_or_else(|| "none".to_string())
+        );
+        limiter.global = limit;
+    }
+}
+
+/// A running download's claim on the global cap; released when dropped.
+pub struct RateSlot {
+    id: u64,
+}
+
+impl RateSlot {
+    /// Joins the downloads sharing the global cap, with an optional cap of its own.
+    pub fn acquire(own_limit: Option<u64>) -> Self {
+        let mut limiter = limiter();
+        let id = limiter.next_id;
+        limiter.next_id += 1;
+        limiter.slots.push((id, own_limit));
+        Self { id }
+    }
+
+    /// The speed this download may use right now, or `None` for no limit.
+    pub fn rate(&self) -> Option<u64> {
+        let limiter = limiter();
+        let caps: Vec<Option<u64>> = limiter.slots.iter().map(|(_, cap)| *cap).collect();
+        let position = limiter.slots.iter().position(|(id, _)| *id == self.id)?;
+        fair_shares(limiter.global, &caps)[position]
+    }
+}
+
+impl Drop for RateSlot {
+    fn drop(&mut self) {
+        limiter().slots.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Splits `global` between downloads with their own `caps`.
+///
+/// Downloads capped below an equal share keep their cap and the bandwidth they
+/// leave unused is divided among the others.
+pub fn fair_shares(global: Option<u64>, caps: &[Option<u64>]) -> Vec<Option<u64>> {
+    let Some(global) = global else {
+        return caps.to_vec();
+    };
+
+    let mut shares: Vec<Option<u64>> = vec![None; caps.len()];
+    let mut remaining = global;
+    let mut open: Vec<usize> = (0..caps.len()).collect();
+
+    loop {
+        if open.is_empty() {
+            return shares;
+        }
+
+        let share = remaining / open.len() as u64;
+        let below: Vec<usize> = open
+            .iter()
+            .copied()
+            .filter(|i| caps[*i].is_some_and(|cap| cap <= share))
+            .collect();
+
+        if below.is_empty() {
+            for i in open {
+                shares[i] = Some(share.max(MIN_RATE));
+            }
+            return shares;
+        }
+
+        for i in below {
+            let cap = caps[i].unwrap_or_default();
+            shares[i] = Some(cap);
+            remaining -= cap;
+            open.retain(|o| *o != i);
+        }
+    }
+}
+
+/// Reads a speed such as "500K", "2.5M" or "1048576" in bytes per second.
+///
+/// Units are binary, as in yt-dlp; an empty text or zero means no limit.
+pub fn parse_rate(text: &str) -> Result<Option<u64>> {
+    let invalid = || DownloadError::InvalidRate(text.trim().to_string());
+
+    let lower = text.trim().to_lowercase();
+    let number = lower
+        .trim_end_matches("/s")
+        .trim_end_matches('b')
+        .trim_end_matches('i');
+    if number.is_empty() {
+        return Ok(None);
+    }
+
+    let (digits, multiplier) = match number.chars().last() {
+        Some('k') => (&number[..number.len() - 1], 1024.0),
+        Some('m') => (&number[..number.len() - 1], 1024.0 * 1024.0),
+        Some('g') => (&number[..number.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (number, 1.0),
+    };
+
+    let value: f64 = digits.trim().parse().map_err(|_| invalid())?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid());
+    }
+
+    let bytes = (value * multiplier).round() as u64;
+    Ok((bytes > 0).then_some(bytes))
+}
+
+/// Formats a speed as "2.5 MiB/s".
+pub fn format_rate(bytes_per_second: u64) -> String {
+    format!("{}/s", format_bytes(bytes_per_second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fair_shares() {
+        const M: u64 = 1024 * 1024;
+
+        assert_eq!(fair_shares(None, &[None, Some(M)]), vec![None, Some(M)]);
+        assert_eq!(
+            fair_shares(Some(6 * M), &[None, None, None]),
+            vec![Some(2 * M); 3]
+        );
+        // The capped download leaves its unused share to the others
+        assert_eq!(
+            fair_shares(Some(6 * M), &[Some(M), None, None]),
+            vec![Some(M), Some(5 * M / 2), Some(5 * M / 2)]
+        );
+        assert_eq!(fair_shares(Some(6 * M), &[Some(10 * M)]), vec![Some(6 * M)]);
+        assert_eq!(fair_shares(Some(1), &[None]), vec![Some(MIN_RATE)]);
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("500K").unwrap(), Some(500 * 1024));
+        assert_eq!(parse_rate("2.5M").unwrap(), Some(2_621_440));
+        assert_eq!(parse_rate("1 MiB/s").unwrap(), Some(1024 * 1024));
+        assert_eq!(parse_rate("1048576").unwrap(), Some(1024 * 1024));
+        assert_eq!(parse_rate("").unwrap(), None);
+        assert_eq!(parse_rate("0").unwrap(), None);
+        assert!(parse_rate("fast").is_err());
+        assert!(parse_rate("-1M").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  cancel.rs                                            TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 09 2025 10:12 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 09 2025 10:12 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use log::{debug, info, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared flag used to ask a running download to stop.
+///
+/// Clones share the same state, so the UI can keep one copy and hand the
+/// other to `VideoDownloader::download`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    /// Set when the download should be resumed later rather than discarded
+    keep_partial_files: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        info!("Cancellation requested");
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops the download like `cancel`, but leaves its partial files to resume from.
+    pub fn interrupt(&self) {
+        self.keep_partial_files.store(true, Ordering::SeqCst);
+        self.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn keeps_partial_files(&self) -> bool {
+        self.keep_partial_files.load(Ordering::SeqCst)
+    }
+}
+
+/// Background thread that kills a child process tree once its token is cancelled.
+///
+/// The watcher stops when dropped, so it only lives as long as the caller is
+/// waiting on the child.
+pub struct CancelWatcher {
+    done: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl CancelWatcher {
+    pub fn spawn(pid: u32, token: CancellationToken) -> Self {
+        let done = Arc::new(AtomicBool::new(false));
+        let done_clone = done.clone();
+
+        let handle = std::thread::spawn(move || {
+            while !done_clone.load(Ordering::SeqCst) {
+                if token.is_cancelled() {
+                    terminate_process_tree(pid);
+                    return;
+                }
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+        });
+
+        Self {
+            done,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for CancelWatcher {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Terminates yt-dlp together with the ffmpeg processes it spawned.
+///
+/// On Unix the child is started in its own process group (see
+/// `VideoDownloader`), so signalling the negative pid reaches the whole group.
+#[cfg(unix)]
+pub fn terminate_process_tree(pid: u32) {
+    info!("Terminating process group {}", pid);
+    let group = format!("-{}", pid);
+
+    let terminated = Command::new("kill")
+        .args(["-TERM", "--", &group])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    if !terminated {
+        warn!("SIGTERM to process group {} failed", pid);
+        return;
+    }
+
+    // Give ffmpeg a moment to flush, then make sure nothing survives
+    std::thread::sleep(Duration::from_millis(500));
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &group])
+        .stderr(std::process::Stdio::null())
+        .status();
+}
+
+#[cfg(windows)]
+pub fn terminate_process_tree(pid: u32) {
+    info!("Terminating process tree {}", pid);
+    let status = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .status();
+
+    if let Err(e) = status {
+        warn!("Failed to run taskkill for {}: {}", pid, e);
+    }
+}
+
+/// Returns true for the temporary files yt-dlp leaves behind while downloading.
+pub fn is_partial_file(name: &str) -> bool {
+    name.ends_with(".part") || name.ends_with(".ytdl") || name.contains(".part-Frag")
+}
+
+/// Partial download files anywhere under `dir`, to compare against after a cancel.
+pub fn snapshot_partial_files(dir: &Path) -> HashSet<PathBuf> {
+    let mut found = HashSet::new();
+    collect_partial_files(dir, &mut found);
+    found
+}
+
+/// Walks `dir` and its subdirectories, since templates may place files in subfolders.
+///
+/// Symlinked directories are not followed.
+fn collect_partial_files(dir: &Path, found: &mut HashSet<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Cannot scan {} for partial files: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            collect_partial_files(&entry.path(), found);
+        } else if entry.file_name().to_str().is_some_and(is_partial_file) {
+            found.insert(entry.path());
+        }
+    }
+}
+
+/// Removes partial download files that appeared under `dir` since `before` was taken.
+pub fn cleanup_partial_files(dir: &Path, before: &HashSet<PathBuf>) {
+    for path in snapshot_partial_files(dir).difference(before) {
+        debug!("Removing partial file: {}", path.display());
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove partial file {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(!clone.keeps_partial_files());
+
+        let interrupted = CancellationToken::new();
+        interrupted.interrupt();
+        assert!(interrupted.is_cancelled());
+        assert!(interrupted.keeps_partial_files());
+    }
+
+    #[test]
+    fn test_is_partial_file() {
+        assert!(is_partial_file("video.mp4.part"));
+        assert!(is_partial_file("video.mp4.ytdl"));
+        assert!(is_partial_file("video.f137.mp4.part-Frag12"));
+        assert!(!is_partial_file("video.mp4"));
+        assert!(!is_partial_file("partial.mkv"));
+    }
+
+    #[test]
+    fn test_cleanup_partial_files_keeps_existing() {
+        let dir = std::env::temp_dir().join(format!("vdl_cancel_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(dir.join("old.mp4.part"), b"old").unwrap();
+        let before = snapshot_partial_files(&dir);
+        assert_eq!(before.len(), 1);
+
+        std::fs::write(dir.join("new.mp4.part"), b"new").unwrap();
+        std::fs::write(dir.join("new.mp4.ytdl"), b"new").unwrap();
+        std::fs::write(dir.join("done.mp4"), b"done").unwrap();
+
+        cleanup_partial_files(&dir, &before);
+
+        assert!(dir.join("old.mp4.part").exists());
+        assert!(!dir.join("new.mp4.part").exists());
+        assert!(!dir.join("new.mp4.ytdl").exists());
+        assert!(dir.join("done.mp4").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cleanup_partial_files_in_subfolders() {
+        let dir = std::env::temp_dir().join(format!("vdl_cancel_nested_{}", std::process::id()));
+        let nested = dir.join("Channel").join("2025");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        std::fs::write(nested.join("old.mp4.part"), b"old").unwrap();
+        let before = snapshot_partial_files(&dir);
+
+        std::fs::write(nested.join("new.f137.mp4.part-Frag3"), b"new").unwrap();
+        std::fs::write(nested.join("new.mp4.ytdl"), b"new").unwrap();
+        std::fs::write(nested.join("done.mp4"), b"done").unwrap();
+
+        cleanup_partial_files(&dir, &before);
+
+        assert!(nested.join("old.mp4.part").exists());
+        assert!(!nested.join("new.f137.mp4.part-Frag3").exists());
+        assert!(!nested.join("new.mp4.ytdl").exists());
+        assert!(nested.join("done.mp4").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,246 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  chapters.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 13 2025 11:00 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 13 2025 11:00 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::Result;
+use crate::core::settings::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// File name of each chapter inside the video's folder.
+const CHAPTER_NAME: &str = "%(section_number)03d - %(section_title)s.%(ext)s";
+
+/// Writes every chapter of a video to its own file, in a folder named after the video.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChapterSplit {
+    /// Also write an M3U playlist of the parts, in chapter order
+    pub write_playlist: bool,
+}
+
+/// A chapter as yt-dlp reports it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ChapterInfo {
+    pub title: Option<String>,
+    pub start_time: Option<f64>,
+    pub end_time: Option<f64>,
+}
+
+impl ChapterInfo {
+    fn duration(&self) -> Option<f64> {
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) if end > start => Some(end - start),
+            _ => None,
+        }
+    }
+}
+
+impl ChapterSplit {
+    /// Moves the full video into a folder named like it: "a/Title.%(ext)s"
+    /// becomes "a/Title/Title.%(ext)s".
+    pub fn main_template(template: &str) -> String {
+        let (dir, stem, ext) = split_template(template);
+        format!("{}{}/{}{}", dir, stem, stem, ext)
+    }
+
+    /// yt-dlp options splitting the download named by `main_template` into chapters next to it.
+    pub fn yt_dlp_args(&self, main_template: &str) -> Vec<String> {
+        let folder = match main_template.rfind('/') {
+            Some(slash) => &main_template[..=slash],
+            None => "",
+        };
+
+        vec![
+            "--split-chapters".to_string(),
+            "-o".to_string(),
+            format!("chapter:{}{}", folder, CHAPTER_NAME),
+        ]
+    }
+
+    /// Finds the chapter files written next to `video` and, if asked, lists them in an M3U.
+    ///
+    /// Returns the chapter files in order and the playlist, if one was written.
+    pub fn collect(
+        &self,
+        video: &Path,
+        chapters: &[ChapterInfo],
+    ) -> Result<(Vec<PathBuf>, Option<PathBuf>)> {
+        let Some(folder) = video.parent() else {
+            return Ok((Vec::new(), None));
+        };
+
+        let files = find_chapter_files(folder, video);
+        if files.is_empty() || !self.write_playlist {
+            return Ok((files, None));
+        }
+
+        let stem = video
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "chapters".to_string());
+        let playlist = folder.join(format!("{}.m3u", stem));
+        write_atomically(&playlist, m3u_contents(&files, chapters).as_bytes())?;
+
+        Ok((files, Some(playlist)))
+    }
+}
+
+/// Splits "a/b/Title.%(ext)s" into ("a/b/", "Title", ".%(ext)s").
+fn split_template(template: &str) -> (&str, &str, &str) {
+    let (dir, name) = match template.rfind('/') {
+        Some(slash) => template.split_at(slash + 1),
+        None => ("", template),
+    };
+
+    match name.rfind(".%(ext)s") {
+        Some(dot) => (dir, &name[..dot], &name[dot..]),
+        None => (dir, name, ""),
+    }
+}
+
+/// Chapter files yt-dlp wrote into `folder`, which start with their three digit index.
+fn find_chapter_files(folder: &Path, video: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path != video && path.is_file())
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            // Other files next to the video may start with any character
+            name.get(..3)
+                .is_some_and(|index| index.bytes().all(|b| b.is_ascii_digit()))
+                && name[3..].starts_with(" - ")
+                && name.len() > 6
+                && !name.ends_with(".part")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Extended M3U with relative paths, so the folder can be moved as a whole.
+fn m3u_contents(files: &[PathBuf], chapters: &[ChapterInfo]) -> String {
+    let mut contents = String::from("#EXTM3U\n");
+
+    for (i, file) in files.iter().enumerate() {
+        let chapter = chapters.get(i).filter(|_| chapters.len() == files.len());
+        let seconds = chapter
+            .and_then(ChapterInfo::duration)
+            .map(|d| d.round() as i64)
+            .unwrap_or(-1);
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let title = chapter
+            .and_then(|c| c.title.clone())
+            .unwrap_or_else(|| name.clone());
+
+        contents.push_str(&format!("#EXTINF:{},{}\n{}\n", seconds, title, name));
+    }
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapter_templates() {
+        let main = ChapterSplit::main_template("%(uploader)s/%(title)s.%(ext)s");
+        assert_eq!(main, "%(uploader)s/%(title)s/%(title)s.%(ext)s");
+        assert_eq!(
+            ChapterSplit::default().yt_dlp_args(&main),
+            vec![
+                "--split-chapters",
+                "-o",
+                "chapter:%(uploader)s/%(title)s/%(section_number)03d - %(section_title)s.%(ext)s"
+            ]
+        );
+        assert_eq!(
+            ChapterSplit::main_template("%(title)s.%(ext)s"),
+            "%(title)s/%(title)s.%(ext)s"
+        );
+    }
+
+    #[test]
+    fn test_collect_writes_playlist() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_chapters_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Talk.mkv");
+        for name in [
+            "Talk.mkv",
+            "002 - Outro.mkv",
+            "001 - Intro.mkv",
+            "Talk.info.json",
+        ] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        let chapters = vec![
+            ChapterInfo {
+                title: Some("Intro".to_string()),
+                start_time: Some(0.0),
+                end_time: Some(61.2),
+            },
+            ChapterInfo {
+                title: Some("Outro".to_string()),
+                start_time: Some(61.2),
+                end_time: Some(90.0),
+            },
+        ];
+
+        let split = ChapterSplit {
+            write_playlist: true,
+        };
+        let (files, playlist) = split.collect(&video, &chapters).unwrap();
+
+        assert_eq!(
+            files,
+            vec![dir.join("001 - Intro.mkv"), dir.join("002 - Outro.mkv")]
+        );
+        assert_eq!(playlist, Some(dir.join("Talk.m3u")));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Talk.m3u")).unwrap(),
+            "#EXTM3U\n#EXTINF:61,Intro\n001 - Intro.mkv\n#EXTINF:29,Outro\n002 - Outro.mkv\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_find_chapter_files_skips_non_ascii_names() {
+        let dir =
+            std::env::temp_dir().join(format!("vdownloader_chapters_ru_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("Лекция.mkv");
+        for name in ["Лекция.mkv", "Лекция.ru.srt", "001 - Введение.mkv", "Я.txt"]
+        {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+
+        assert_eq!(
+            find_chapter_files(&dir, &video),
+            vec![dir.join("001 - Введение.mkv")]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,292 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  clip.rs                                              TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 12 2025 15:30 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 12 2025 15:30 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A segment of a video, in seconds from its start.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct TimeRange {
+    pub start: f64,
+    /// `None` runs to the end of the video
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// Value for yt-dlp's `--download-sections`, e.g. `*90-165.5` or `*600-inf`.
+    pub fn to_section(self) -> String {
+        let end = self
+            .end
+            .map(format_seconds)
+            .unwrap_or_else(|| "inf".to_string());
+        format!("*{}-{}", format_seconds(self.start), end)
+    }
+}
+
+/// Written the way `parse_ranges` reads it, e.g. "1:30-2:45.5" or "10:00-".
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-", format_timestamp(self.start))?;
+        if let Some(end) = self.end {
+            write!(f, "{}", format_timestamp(end))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parts of a video to download instead of the whole of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClipOptions {
+    pub ranges: Vec<TimeRange>,
+    /// Re-encode around the cuts so they land exactly on the marks instead of
+    /// the nearest keyframes; slower
+    pub precise: bool,
+}
+
+impl ClipOptions {
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for range in &self.ranges {
+            args.push("--download-sections".to_string());
+            args.push(range.to_section());
+        }
+
+        if self.precise && !self.ranges.is_empty() {
+            args.push("--force-keyframes-at-cuts".to_string());
+        }
+
+        args
+    }
+
+    /// Marks clip file names with their start so several clips of one video,
+    /// or a clip and the full video, do not collide.
+    pub fn output_template(&self, template: &str) -> String {
+        const SUFFIX: &str = " (clip from %(section_start)ds)";
+
+        if self.ranges.is_empty() {
+            return template.to_string();
+        }
+
+        match template.rfind(".%(ext)s") {
+            Some(pos) => format!("{}{}{}", &template[..pos], SUFFIX, &template[pos..]),
+            None => template.to_string(),
+        }
+    }
+}
+
+/// Parses a list such as "1:30-2:45, 10:00-" into time ranges.
+///
+/// An empty start means the beginning and an empty end means the end of the video.
+pub fn parse_ranges(spec: &str) -> Result<Vec<TimeRange>> {
+    let invalid = || DownloadError::InvalidTimeRange(spec.trim().to_string());
+
+    spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (start, end) = part.split_once('-').ok_or_else(invalid)?;
+            let (start, end) = (start.trim(), end.trim());
+            if start.is_empty() && end.is_empty() {
+                return Err(invalid());
+            }
+
+            let start = if start.is_empty() {
+                0.0
+            } else {
+                parse_timestamp(start).ok_or_else(invalid)?
+            };
+            let end = if end.is_empty() {
+                None
+            } else {
+                Some(parse_timestamp(end).ok_or_else(invalid)?)
+            };
+
+            if end.is_some_and(|end| end <= start) {
+                return Err(invalid());
+            }
+            Ok(TimeRange { start, end })
+        })
+        .collect()
+}
+
+/// Reads "90", "90.5", "1:30", "1:02:03" or "1h2m3s" as seconds.
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let seconds = if text.contains(':') {
+        let parts: Vec<&str> = text.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        parts.iter().try_fold(0.0, |total, part| {
+            let value: f64 = part.parse().ok()?;
+            (value >= 0.0).then_some(total * 60.0 + value)
+        })?
+    } else if text.ends_with(['h', 'm', 's']) {
+        let mut total = 0.0;
+        let mut number = String::new();
+        for c in text.chars() {
+            match c {
+                '0'..='9' | '.' => number.push(c),
+                'h' | 'm' | 's' => {
+                    let value: f64 = number.parse().ok()?;
+                    total += value
+                        * match c {
+                            'h' => 3600.0,
+                            'm' => 60.0,
+                            _ => 1.0,
+                        };
+                    number.clear();
+                }
+                _ => return None,
+            }
+        }
+        total
+    } else {
+        text.parse().ok()?
+    };
+
+    (seconds.is_finite() && seconds >= 0.0).then_some(seconds)
+}
+
+/// Formats seconds as "1:02:03", "1:30" or "0:05.5".
+pub fn format_timestamp(seconds: f64) -> String {
+    let tenths = (seconds * 10.0).round() as u64;
+    let whole = tenths / 10;
+    let (h, m, s) = (whole / 3600, (whole % 3600) / 60, whole % 60);
+
+    let mut text = if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    };
+    if !tenths.is_multiple_of(10) {
+        text.push_str(&format!(".{}", tenths % 10));
+    }
+    text
+}
+
+fn format_seconds(seconds: f64) -> String {
+    let rounded = (seconds * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as u64)
+    } else {
+        format!("{:.1}", rounded)
+    }
+}
+
+/// Start time carried by a shared link, such as `?t=90`, `&t=1m30s` or `#t=90`.
+pub fn start_from_url(url: &str) -> Option<f64> {
+    let params = url.split_once(['?', '#'])?.1;
+
+    params
+        .split(['&', '?', '#'])
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, _)| matches!(*key, "t" | "start" | "time_continue"))
+        .find_map(|(_, value)| parse_timestamp(value))
+        .filter(|seconds| *seconds > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("90"), Some(90.0));
+        assert_eq!(parse_timestamp("1:30"), Some(90.0));
+        assert_eq!(parse_timestamp("1:02:03.5"), Some(3723.5));
+        assert_eq!(parse_timestamp("1h2m3s"), Some(3723.0));
+        assert_eq!(parse_timestamp("45s"), Some(45.0));
+        assert_eq!(parse_timestamp("1:x"), None);
+        assert_eq!(parse_timestamp("-5"), None);
+        assert_eq!(parse_timestamp("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        let ranges = parse_ranges("1:30-2:45.5, 10:00-, -0:20").unwrap();
+        assert_eq!(
+            ranges,
+            vec![
+                TimeRange {
+                    start: 90.0,
+                    end: Some(165.5)
+                },
+                TimeRange {
+                    start: 600.0,
+                    end: None
+                },
+                TimeRange {
+                    start: 0.0,
+                    end: Some(20.0)
+                },
+            ]
+        );
+        assert_eq!(ranges[0].to_section(), "*90-165.5");
+        assert_eq!(ranges[1].to_section(), "*600-inf");
+        assert_eq!(ranges[0].to_string(), "1:30-2:45.5");
+        assert_eq!(ranges[1].to_string(), "10:00-");
+
+        assert!(parse_ranges("").unwrap().is_empty());
+        assert!(parse_ranges("2:00-1:00").is_err());
+        assert!(parse_ranges("-").is_err());
+        assert!(parse_ranges("1:30").is_err());
+    }
+
+    #[test]
+    fn test_start_from_url() {
+        assert_eq!(start_from_url("https://youtu.be/abc?t=90"), Some(90.0));
+        assert_eq!(
+            start_from_url("https://www.youtube.com/watch?v=abc&t=1m30s"),
+            Some(90.0)
+        );
+        assert_eq!(
+            start_from_url("https://rutube.ru/video/abc/#t=45"),
+            Some(45.0)
+        );
+        assert_eq!(start_from_url("https://www.youtube.com/watch?v=abc"), None);
+        assert_eq!(start_from_url("https://youtu.be/abc?t=0"), None);
+    }
+
+    #[test]
+    fn test_clip_args_and_template() {
+        let clip = ClipOptions {
+            ranges: parse_ranges("1:00-2:00, 5:00-").unwrap(),
+            precise: true,
+        };
+
+        assert_eq!(
+            clip.yt_dlp_args(),
+            vec![
+                "--download-sections",
+                "*60-120",
+                "--download-sections",
+                "*300-inf",
+                "--force-keyframes-at-cuts"
+            ]
+        );
+        assert_eq!(
+            clip.output_template("%(title)s.%(ext)s"),
+            "%(title)s (clip from %(section_start)ds).%(ext)s"
+        );
+        assert_eq!(
+            ClipOptions::default().output_template("%(title)s.%(ext)s"),
+            "%(title)s.%(ext)s"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  conflict.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 16:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 16:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::progress::format_bytes;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// What to do when the file a download would produce already exists.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the existing file and report the download as skipped
+    #[default]
+    Skip,
+    /// Replace the existing file
+    Overwrite,
+    /// Download next to it as "Title (1).ext", "Title (2).ext", ...
+    Rename,
+    /// Let the user decide before the download starts; behaves like `Skip`
+    /// when nobody can be asked
+    Ask,
+}
+
+impl ConflictPolicy {
+    pub const ALL: [ConflictPolicy; 4] = [
+        ConflictPolicy::Skip,
+        ConflictPolicy::Overwrite,
+        ConflictPolicy::Rename,
+        ConflictPolicy::Ask,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "Skip",
+            ConflictPolicy::Overwrite => "Overwrite",
+            ConflictPolicy::Rename => "Keep both (rename)",
+            ConflictPolicy::Ask => "Ask",
+        }
+    }
+
+    /// yt-dlp options enforcing this policy on the files it writes.
+    pub fn yt_dlp_args(&self) -> &'static [&'static str] {
+        match self {
+            ConflictPolicy::Overwrite => &["--force-overwrites"],
+            // A renamed target is free, so nothing may be overwritten either way
+            _ => &["--no-overwrites", "--no-post-overwrites"],
+        }
+    }
+
+    /// Whether the target has to be predicted before downloading.
+    ///
+    /// yt-dlp notices an existing video itself, but not an existing converted
+    /// or remuxed file, which it would only find after downloading the source again.
+    pub fn needs_prediction(&self, converts: bool) -> bool {
+        match self {
+            ConflictPolicy::Overwrite => false,
+            ConflictPolicy::Rename => true,
+            ConflictPolicy::Skip | ConflictPolicy::Ask => converts,
+        }
+    }
+}
+
+/// The file a download is about to produce, as predicted by yt-dlp.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct IncomingFile {
+    #[serde(rename = "filename")]
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    pub id: Option<String>,
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub filesize: Option<f64>,
+    pub filesize_approx: Option<f64>,
+    pub playlist_id: Option<serde_json::Value>,
+}
+
+impl IncomingFile {
+    pub const TEMPLATE: &'static str = "%(.{filename,title,uploader,id,duration,width,height,filesize,filesize_approx,playlist_id})j";
+
+    pub fn size(&self) -> Option<u64> {
+        self.filesize
+            .or(self.filesize_approx)
+            .filter(|s| s.is_finite() && *s > 0.0)
+            .map(|s| s as u64)
+    }
+
+    pub fn is_playlist_entry(&self) -> bool {
+        self.playlist_id.as_ref().is_some_and(|id| !id.is_null())
+    }
+}
+
+/// An existing file standing where a download would write.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileConflict {
+    pub existing_size: Option<u64>,
+    pub existing_resolution: Option<(u32, u32)>,
+    pub incoming: IncomingFile,
+}
+
+impl FileConflict {
+    /// Looks at the file on disk, or returns `None` when the target is free.
+    pub fn detect(incoming: IncomingFile) -> Option<Self> {
+        let metadata = std::fs::metadata(&incoming.path).ok()?;
+        Some(Self {
+            existing_size: Some(metadata.len()),
+            existing_resolution: probe_resolution(&incoming.path),
+            incoming,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.incoming.path
+    }
+
+    /// "1920x1080 • 1.2 GiB" style description of the file on disk.
+    pub fn describe_existing(&self) -> String {
+        describe(self.existing_resolution, self.existing_size)
+    }
+
+    /// The same description for the file about to be downloaded.
+    pub fn describe_incoming(&self) -> String {
+        let resolution = self.incoming.width.zip(self.incoming.height);
+        describe(resolution, self.incoming.size())
+    }
+}
+
+fn describe(resolution: Option<(u32, u32)>, size: Option<u64>) -> String {
+    let mut parts = Vec::new();
+    if let Some((width, height)) = resolution {
+        parts.push(format!("{}x{}", width, height));
+    }
+    if let Some(size) = size {
+        parts.push(format_bytes(size));
+    }
+
+    if parts.is_empty() {
+        "unknown size".to_string()
+    } else {
+        parts.join(" • ")
+    }
+}
+
+/// Reads the video resolution of a local file with ffprobe, if it is available.
+fn probe_resolution(path: &Path) -> Option<(u32, u32)> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height", "-of", "csv=p=0"])
+        .arg(path)
+        .output()
+        .ok()?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (width, height) = text.trim().split_once(',')?;
+    Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+}
+
+/// First "name (N).ext" next to `path` that does not exist yet.
+pub fn next_free_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .expect("an unused file name")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_free_path() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_conflict_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let original = dir.join("Talk.mp4");
+        assert_eq!(next_free_path(&original), dir.join("Talk (1).mp4"));
+
+        std::fs::write(dir.join("Talk (1).mp4"), b"").unwrap();
+        assert_eq!(next_free_path(&original), dir.join("Talk (2).mp4"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_incoming_file_report() {
+        let json = r#"{"filename": "/videos/Talk.mkv", "title": "Talk", "width": 1920, "height": 1080, "filesize": null, "filesize_approx": 1536.0, "playlist_id": null}"#;
+        let incoming: IncomingFile = serde_json::from_str(json).unwrap();
+
+        assert_eq!(incoming.path, PathBuf::from("/videos/Talk.mkv"));
+        assert_eq!(incoming.size(), Some(1536));
+        assert!(!incoming.is_playlist_entry());
+
+        let conflict = FileConflict {
+            existing_size: None,
+            existing_resolution: None,
+            incoming,
+        };
+        assert_eq!(conflict.describe_incoming(), "1920x1080 • 1.5 KiB");
+        assert_eq!(conflict.describe_existing(), "unknown size");
+    }
+
+    #[test]
+    fn test_policy_args() {
+        assert_eq!(
+            ConflictPolicy::Overwrite.yt_dlp_args(),
+            &["--force-overwrites"]
+        );
+        assert!(ConflictPolicy::Skip
+            .yt_dlp_args()
+            .contains(&"--no-post-overwrites"));
+        assert!(ConflictPolicy::Rename.needs_prediction(false));
+        assert!(!ConflictPolicy::Skip.needs_prediction(false));
+        assert!(ConflictPolicy::Ask.needs_prediction(true));
+    }
+}
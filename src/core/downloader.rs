@@ -11,16 +11,171 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+use crate::core::archive::{ArchiveEntry, DownloadArchive};
+use crate::core::auth;
+use crate::core::bandwidth::{format_rate, RateSlot};
+use crate::core::cancel::{
+    cleanup_partial_files, snapshot_partial_files, terminate_process_tree, CancelWatcher,
+    CancellationToken,
+};
+use crate::core::chapters::{ChapterInfo, ChapterSplit};
+use crate::core::clip::ClipOptions;
+use crate::core::conflict::{next_free_path, ConflictPolicy, FileConflict, IncomingFile};
+use crate::core::embed::EmbedOptions;
 use crate::core::error::{DownloadError, Result};
+use crate::core::format::{parse_formats, AudioExtraction, FormatInfo, FormatSpec};
+use crate::core::network;
+use crate::core::outcome::DownloadOutcome;
+use crate::core::playlist::{
+    playlist_folder_name, PlaylistDownloadResult, PlaylistEntry, PlaylistEntryFailure,
+    PlaylistProgress, PlaylistSelection,
+};
+use crate::core::progress::{DownloadProgress, DownloadStage};
+use crate::core::sponsorblock::SponsorBlockOptions;
+use crate::core::subtitles::{parse_subtitles, SubtitleOptions, SubtitleTrack};
+use crate::core::template::{validate_template, DEFAULT_TEMPLATE, TRIM_FILENAME_CHARS};
 use log::{debug, info, warn};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use youtube_dl::{YoutubeDl, YoutubeDlOutput};
+use std::time::{Duration, Instant, SystemTime};
+use youtube_dl::{Playlist, YoutubeDl, YoutubeDlOutput};
+
+/// Prefix of the line yt-dlp prints with the final paths of each produced item.
+const FINAL_PATH_MARKER: &str = "VDOWNLOADER_FILE:";
+/// Prefix of the line yt-dlp prints with metadata before it processes an item.
+const MEDIA_INFO_MARKER: &str = "VDOWNLOADER_INFO:";
+
+/// Metadata yt-dlp prints for an item right before downloading it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct MediaInfo {
+    id: Option<String>,
+    title: Option<String>,
+    uploader: Option<String>,
+    duration: Option<f64>,
+    width: Option<u32>,
+    height: Option<u32>,
+    vcodec: Option<String>,
+    acodec: Option<String>,
+    playlist_id: Option<serde_json::Value>,
+    playlist_title: Option<String>,
+}
+
+impl MediaInfo {
+    const TEMPLATE: &'static str =
+        "%(.{id,title,uploader,duration,width,height,vcodec,acodec,playlist_id,playlist_title})j";
+
+    fn is_playlist_entry(&self) -> bool {
+        self.playlist_id.as_ref().is_some_and(|id| !id.is_null())
+    }
+}
+
+/// Files yt-dlp produced for one item, exactly as it reported them after moving.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadedFiles {
+    pub path: PathBuf,
+    /// Subtitles, thumbnails and info.json written next to the media file
+    pub side_files: Vec<PathBuf>,
+    /// The subtitle files among `side_files`
+    pub subtitles: Vec<PathBuf>,
+    /// Languages of all fetched subtitles, including embedded ones
+    pub subtitle_languages: Vec<String>,
+    /// yt-dlp found the file already present and left it alone
+    pub skipped: bool,
+    /// Chapters of the video, used to describe split chapter files
+    pub chapters: Vec<ChapterInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ReportedFiles {
+    filepath: Option<String>,
+    requested_subtitles: Option<HashMap<String, ReportedSideFile>>,
+    thumbnails: Option<Vec<ReportedSideFile>>,
+    #[serde(rename = "__infojson_filename")]
+    infojson_filename: Option<String>,
+    #[serde(rename = "__real_download")]
+    real_download: Option<bool>,
+    chapters: Option<Vec<ChapterInfo>>,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ReportedSideFile {
+    filepath: Option<String>,
+}
+
+impl DownloadedFiles {
+    const TEMPLATE: &'static str =
+        "%(.{filepath,requested_subtitles,thumbnails,__infojson_filename,__real_download,chapters})j";
+
+    /// Parses the JSON printed for `after_move`, resolving relative paths against `base_dir`.
+    fn from_report(json: &str, base_dir: &Path) -> Option<Self> {
+        let reported: ReportedFiles = serde_json::from_str(json).ok()?;
+        let path = base_dir.join(reported.filepath?);
+        let skipped = reported.real_download == Some(false);
+
+        let requested_subtitles = reported.requested_subtitles.unwrap_or_default();
+        let mut subtitle_languages: Vec<String> = requested_subtitles.keys().cloned().collect();
+        subtitle_languages.sort();
+
+        let mut subtitles: Vec<PathBuf> = requested_subtitles
+            .into_values()
+            .filter_map(|f| f.filepath)
+            .map(|f| base_dir.join(f))
+            .filter(|f| *f != path)
+            .collect();
+        subtitles.sort();
+
+        let mut side_files: Vec<PathBuf> = reported
+            .thumbnails
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|f| f.filepath)
+            .chain(reported.infojson_filename)
+            .map(|f| base_dir.join(f))
+            .filter(|f| *f != path)
+            .chain(subtitles.iter().cloned())
+            .collect();
+        side_files.sort();
+        side_files.dedup();
+
+        Some(Self {
+            path,
+            side_files,
+            subtitles,
+            subtitle_languages,
+            skipped,
+            chapters: reported.chapters.unwrap_or_default(),
+        })
+    }
+}
+
+/// What to run for one item once the conflict policy has been applied.
+enum TargetPlan {
+    /// Download using this output template
+    Download(String),
+    /// The file already exists and is kept as it is
+    Keep(Box<DownloadOutcome>),
+}
+
+/// How a yt-dlp download process ended.
+enum CommandRun {
+    Finished {
+        media: Option<MediaInfo>,
+        files: Vec<DownloadedFiles>,
+    },
+    /// Stopped because yt-dlp began working through a playlist
+    PlaylistDetected(MediaInfo),
+    /// Stopped before downloading because the archive lists the video
+    Archived(ArchiveEntry),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Platform {
     YouTube,
     TikTok,
@@ -30,25 +185,130 @@ pub enum Platform {
     Vk,
     Rutube,
     Dzen,
+    #[default]
     Other,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DownloadRequest {
     pub url: String,
     pub platform: Platform,
     pub output_path: Option<String>,
+    /// What to do when the target file already exists
+    #[serde(default)]
+    pub conflict: ConflictPolicy,
+    #[serde(default)]
+    pub format: FormatSpec,
+    /// Extract the audio track and convert it instead of keeping the video
+    #[serde(default)]
+    pub audio: Option<AudioExtraction>,
+    /// Entries to fetch when the URL turns out to be a playlist
     #[serde(default)]
-    pub overwrite: bool,
+    pub playlist: PlaylistSelection,
+    /// yt-dlp output template relative to the output directory
+    #[serde(default)]
+    pub filename_template: Option<String>,
+    /// Subtitle languages to save or embed; empty fetches none
+    #[serde(default)]
+    pub subtitles: SubtitleOptions,
+    /// Tags, cover art and chapters written into the finished file
+    #[serde(default)]
+    pub embed: EmbedOptions,
+    /// Time ranges to download instead of the whole video
+    #[serde(default)]
+    pub clip: ClipOptions,
+    /// Also write each chapter to its own file, in a folder named after the video
+    #[serde(default)]
+    pub split_chapters: Option<ChapterSplit>,
+    /// Sponsor, intro and similar segments to cut out or mark; YouTube only
+    #[serde(default)]
+    pub sponsorblock: SponsorBlockOptions,
+    /// Download even if the download archive lists the video
+    #[serde(default)]
+    pub redownload: bool,
+    /// Own speed cap in bytes per second, applied within the share of the global cap
+    #[serde(default)]
+    pub rate_limit: Option<u64>,
+    /// Speed in bytes per second below which yt-dlp assumes throttling and re-extracts
+    #[serde(default)]
+    pub throttled_rate: Option<u64>,
+    /// Continue the partial files of a paused or interrupted run; with
+    /// `ConflictPolicy::Overwrite` yt-dlp still starts over
+    #[serde(default)]
+    pub resume: bool,
+}
+
+impl DownloadRequest {
+    /// Whether a webm download ends up as mkv, see `EmbedOptions::remuxes_webm`
+    /// and `SubtitleOptions::remuxes_webm`.
+    fn remuxes_webm(&self) -> bool {
+        self.embed.remuxes_webm(&self.format, self.audio.as_ref())
+            || self
+                .subtitles
+                .remuxes_webm(&self.format, self.audio.as_ref())
+    }
+
+    /// Where the file yt-dlp downloads to `path` ends up after conversion or remuxing.
+    fn final_path(&self, mut path: PathBuf) -> PathBuf {
+        if let Some(ref audio) = self.audio {
+            path.set_extension(audio.codec.extension());
+        } else if self.remuxes_webm() && path.extension().is_some_and(|ext| ext == "webm") {
+            path.set_extension("mkv");
+        }
+        path
+    }
+
+    fn filename_template(&self) -> &str {
+        self.filename_template
+            .as_deref()
+            .unwrap_or(DEFAULT_TEMPLATE)
+    }
+
+    /// Adjusts a yt-dlp output template for clips and chapter folders.
+    fn output_template(&self, template: &str) -> String {
+        let template = self.clip.output_template(template);
+        match self.split_chapters {
+            Some(_) => ChapterSplit::main_template(&template),
+            None => template,
+        }
+    }
+
+    /// Format selector for this request; audio extraction always starts from the best audio.
+    fn format_selector(&self) -> Option<String> {
+        if self.audio.is_some() && self.format.format_id.is_none() {
+            return Some("ba/b".to_string());
+        }
+        self.format.selector()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub enum DownloadStatus {
     Pending,
-    Downloading { progress: f32 },
-    Completed { file_path: String },
-    Failed { error: String },
+    Downloading {
+        progress: f32,
+    },
+    /// Stopped by the user with its partial files kept; waits until resumed
+    Paused {
+        progress: f32,
+    },
+    /// Attempt `attempt` of `max_attempts` failed with a transient `error`;
+    /// the item runs again once `next_attempt` has passed
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        next_attempt: SystemTime,
+        error: String,
+    },
+    Completed {
+        outcome: Box<DownloadOutcome>,
+    },
+    PlaylistCompleted {
+        result: Box<PlaylistDownloadResult>,
+    },
+    Failed {
+        error: String,
+    },
 }
 
 pub struct VideoDownloader {
@@ -128,9 +388,16 @@ impl VideoDownloader {
             Err(_) => Err(DownloadError::InvalidOutputDirectory),
         }
     }
-    pub async fn download<F>(&self, request: DownloadRequest, on_progress: F) -> Result<String>
+    /// Downloads `request.url`; a URL that turns out to be a playlist is
+    /// downloaded entry by entry and summarised as one outcome for its folder.
+    pub async fn download<F>(
+        &self,
+        mut request: DownloadRequest,
+        cancel: CancellationToken,
+        on_progress: F,
+    ) -> Result<DownloadOutcome>
     where
-        F: Fn(f32) + Send + Sync + 'static,
+        F: Fn(DownloadProgress) + Send + Sync + 'static,
     {
         info!("Starting download for URL: {}", request.url);
 
@@ -142,14 +409,40 @@ impl VideoDownloader {
         Self::validate_url(&url)?;
         // Self::validate_output_directory(&self.output_directory)?; // Skip directory validation as we might have a file path
 
+        if cancel.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+
+        let warning = Self::check_sponsorblock(&url, &mut request).await;
         let output_path = self.output_directory.clone();
-        let overwrite = request.overwrite;
 
-        tokio::task::spawn_blocking(move || {
-            Self::perform_download(&url, &output_path, overwrite, on_progress)
+        let mut outcome = tokio::task::spawn_blocking(move || {
+            Self::perform_download(&url, &output_path, &request, &cancel, on_progress)
         })
         .await
-        .map_err(|e| DownloadError::DownloadFailed(format!("Task join error: {}", e)))?
+        .map_err(|e| DownloadError::DownloadFailed(format!("Task join error: {}", e)))??;
+
+        outcome.warnings.extend(warning);
+        Ok(outcome)
+    }
+
+    /// Makes sure the SponsorBlock server answers before a YouTube download uses it.
+    ///
+    /// An unreachable server turns SponsorBlock off for this request and is
+    /// returned as a warning instead of failing the download.
+    async fn check_sponsorblock(url: &str, request: &mut DownloadRequest) -> Option<String> {
+        if !request.sponsorblock.is_active() || Self::detect_platform(url) != Platform::YouTube {
+            return None;
+        }
+
+        match request.sponsorblock.check_api().await {
+            Ok(()) => None,
+            Err(e) => {
+                warn!("Downloading without SponsorBlock: {}", e);
+                request.sponsorblock.disable();
+                Some(format!("SponsorBlock skipped: {}", e))
+            }
+        }
     }
 
     fn sanitize_url(url: &str) -> String {
@@ -177,380 +470,1094 @@ impl VideoDownloader {
         url.to_string()
     }
 
+    /// Lists the formats yt-dlp can offer for `url`, for showing a picker before downloading.
+    pub async fn list_formats(url: &str) -> Result<Vec<FormatInfo>> {
+        info!("Fetching available formats for: {}", url);
+        parse_formats(&Self::fetch_info_json(url).await?)
+    }
+
+    /// Lists the manual and auto-generated subtitle languages available for `url`.
+    pub async fn list_subtitles(url: &str) -> Result<Vec<SubtitleTrack>> {
+        info!("Fetching available subtitles for: {}", url);
+        parse_subtitles(&Self::fetch_info_json(url).await?)
+    }
+
+    /// Runs `yt-dlp -J` for a single video and returns its JSON description.
+    async fn fetch_info_json(url: &str) -> Result<String> {
+        let url = Self::sanitize_url(url);
+        Self::validate_url(&url)?;
+
+        let output = tokio::process::Command::new("yt-dlp")
+            .arg("-J")
+            .arg("--no-playlist")
+            .arg("--no-warnings")
+            .args(network::resolve_yt_dlp_args(&url).await)
+            .args(auth::yt_dlp_args(&Self::detect_platform(&url)))
+            .arg(&url)
+            .output()
+            .await
+            .map_err(|e| DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            warn!("Fetching video details failed: {}", stderr.trim());
+            return Err(DownloadError::from_yt_dlp_stderr(&stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
     fn perform_download<F>(
         url: &str,
         output_path: &str,
-        overwrite: bool,
+        request: &DownloadRequest,
+        cancel: &CancellationToken,
         on_progress: F,
-    ) -> Result<String>
+    ) -> Result<DownloadOutcome>
     where
-        F: Fn(f32) + Send + Sync + 'static,
+        F: Fn(DownloadProgress) + Send + Sync + 'static,
     {
         info!("Performing download of {} to {}", url, output_path);
+        let started = Instant::now();
+
+        let template = request.filename_template();
+        validate_template(template)?;
+        let (working_dir, cmd_template) = Self::output_location(output_path, template);
+        let working_dir = working_dir.as_path();
+        let cmd_template = request.output_template(&cmd_template);
+
+        let cmd_template =
+            match Self::plan_target(url, working_dir, &cmd_template, request, started)? {
+                TargetPlan::Download(template) => template,
+                TargetPlan::Keep(outcome) => return Ok(*outcome),
+            };
+
+        info!("Passing to yt-dlp command: {}", cmd_template);
+
+        // Remember what was there so a cancel only removes our own partial files
+        let files_before = snapshot_partial_files(working_dir);
+        let archive = Self::load_archive(request);
+
+        // One invocation both extracts and downloads; metadata arrives on stdout
+        let mut cmd = Self::single_video_command(url, working_dir, request);
+
+        Self::apply_output_args(&mut cmd, working_dir, &cmd_template);
+
+        Self::apply_request_args(&mut cmd, request, &cmd_template);
+
+        let run = match Self::run_download_command(
+            cmd,
+            request.rate_limit,
+            working_dir,
+            &files_before,
+            cancel,
+            &on_progress,
+            true,
+            archive.as_ref().map(|a| (a, &request.platform)),
+        ) {
+            Ok(run) => run,
+            Err(e) if e.to_string().contains("exportResponse") && url.contains("dzen.ru") => {
+                warn!(
+                    "Dzen article/channel URL detected (extractor broken): {}",
+                    e
+                );
+                return Err(DownloadError::DownloadFailed(
+                    "Dzen article/channel URLs are currently not supported by yt-dlp. Please use direct video URLs (dzen.ru/video/watch/...)".to_string(),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
 
-        // If output_path ends with an extension, treat it as a full file path
-        // Otherwise treat it as a directory (legacy behavior, though UI now provides full path)
-        let is_file_path = output_path.ends_with(".mp4")
-            || output_path.ends_with(".mkv")
-            || output_path.ends_with(".webm");
+        let (media, files) = match run {
+            CommandRun::PlaylistDetected(media) => {
+                info!(
+                    "Playlist detected: {}",
+                    media.playlist_title.as_deref().unwrap_or("Playlist")
+                );
 
-        // Determine working directory for temp files
-        let working_dir = if is_file_path {
-            Path::new(output_path).parent().unwrap_or(Path::new("."))
-        } else {
-            Path::new(output_path)
+                let result =
+                    Self::fetch_and_download_playlist(url, working_dir, request, cancel, |p| {
+                        on_progress(p.progress)
+                    })?;
+
+                if !result.failed.is_empty() {
+                    warn!(
+                        "{} playlist entries could not be downloaded",
+                        result.failed.len()
+                    );
+                }
+
+                return Ok(DownloadOutcome {
+                    extra_files: result
+                        .downloads
+                        .iter()
+                        .flat_map(|d| std::iter::once(&d.primary_file).chain(&d.extra_files))
+                        .cloned()
+                        .collect(),
+                    file_size: Some(result.downloads.iter().filter_map(|d| d.file_size).sum()),
+                    primary_file: result.folder,
+                    title: result.title,
+                    elapsed: started.elapsed(),
+                    platform: request.platform.clone(),
+                    ..Default::default()
+                });
+            }
+            CommandRun::Archived(entry) => {
+                info!("{} is already in the download archive", entry.title);
+                return Ok(Self::archived_outcome(&entry, request, started));
+            }
+            CommandRun::Finished { media, files } => (media.unwrap_or_default(), files),
         };
 
-        let output_template = if is_file_path {
-            output_path.to_string()
+        let outcome = Self::build_outcome(media, files, request, started)?;
+        Self::archive_download(request, &outcome);
+        if outcome.skipped {
+            info!(
+                "Skipped {}, it already exists: {}",
+                outcome.title,
+                outcome.primary_file.display()
+            );
         } else {
-            format!("{}/%%(title)s.%%(ext)s", output_path)
+            info!(
+                "Downloaded {}: {}",
+                outcome.title,
+                outcome.primary_file.display()
+            );
+        }
+
+        Ok(outcome)
+    }
+
+    /// Combines what yt-dlp printed before and after a download into one outcome.
+    fn build_outcome(
+        media: MediaInfo,
+        mut files: Vec<DownloadedFiles>,
+        request: &DownloadRequest,
+        started: Instant,
+    ) -> Result<DownloadOutcome> {
+        let title = media.title.unwrap_or_else(|| "video".to_string());
+
+        // The last reported item is the final, post-processed one
+        let Some(last) = files.pop() else {
+            return Err(DownloadError::DownloadFailed(format!(
+                "yt-dlp did not report a file for {}",
+                title
+            )));
         };
 
-        info!("Using output template for validation: {}", output_template);
+        let mut extra_files = last.side_files;
+        let mut subtitle_files = last.subtitles;
+        let mut subtitle_languages = last.subtitle_languages;
+        for earlier in files {
+            extra_files.push(earlier.path);
+            extra_files.extend(earlier.side_files);
+            subtitle_files.extend(earlier.subtitles);
+            subtitle_languages.extend(earlier.subtitle_languages);
+        }
+        subtitle_files.sort();
+        subtitle_files.dedup();
+        subtitle_languages.sort();
+        subtitle_languages.dedup();
+
+        let mut chapter_files = Vec::new();
+        let mut warnings = Vec::new();
+        if let Some(split) = request.split_chapters {
+            match split.collect(&last.path, &last.chapters) {
+                Ok((files, playlist)) => {
+                    info!("Split {} into {} chapter file(s)", title, files.len());
+                    extra_files.extend(files.iter().cloned().chain(playlist));
+                    chapter_files = files;
+                }
+                Err(e) => {
+                    warn!("Failed to write the chapter playlist: {}", e);
+                    warnings.push(format!("Chapter playlist not written: {}", e));
+                }
+            }
+        }
 
-        // The youtube_dl crate needs the template without %% escaping
-        let crate_template = if is_file_path {
-            output_path.to_string()
-        } else {
-            format!("{}/%(title)s.%(ext)s", output_path)
+        let codec = |c: Option<String>| c.filter(|c| c != "none");
+        let (video_codec, audio_codec, width, height) = match request.audio {
+            // Extraction converts the audio and drops the video stream
+            Some(ref audio) => (None, Some(audio.codec.extension().to_string()), None, None),
+            None => (
+                codec(media.vcodec),
+                codec(media.acodec),
+                media.width,
+                media.height,
+            ),
         };
 
-        let result = YoutubeDl::new(url)
-            .socket_timeout("30")
-            .extract_audio(false)
-            .output_template(&crate_template)
-            .extra_arg("--simulate")
-            .run();
-
-        match result {
-            Ok(YoutubeDlOutput::Playlist(_playlist)) => {
-                warn!("Playlist detected, downloading first video only");
-                Self::handle_playlist_download(
-                    url,
-                    output_path,
-                    is_file_path,
-                    overwrite,
+        Ok(DownloadOutcome {
+            file_size: std::fs::metadata(&last.path).ok().map(|m| m.len()),
+            skipped: last.skipped,
+            archived: false,
+            primary_file: last.path,
+            extra_files,
+            subtitle_files,
+            subtitle_languages,
+            chapter_files,
+            title,
+            uploader: media.uploader,
+            video_id: media.id,
+            duration: media
+                .duration
+                .filter(|d| d.is_finite() && *d >= 0.0)
+                .map(Duration::from_secs_f64),
+            width,
+            height,
+            video_codec,
+            audio_codec,
+            elapsed: started.elapsed(),
+            platform: request.platform.clone(),
+            warnings,
+        })
+    }
+
+    /// The download archive to consult, unless the request asks to fetch again.
+    ///
+    /// Clips are only parts of a video, so they neither check nor fill the archive.
+    fn load_archive(request: &DownloadRequest) -> Option<DownloadArchive> {
+        (!request.redownload && request.clip.ranges.is_empty()).then(DownloadArchive::load)
+    }
+
+    /// Remembers a finished download so it is not fetched again.
+    fn archive_download(request: &DownloadRequest, outcome: &DownloadOutcome) {
+        if !request.clip.ranges.is_empty() || outcome.archived {
+            return;
+        }
+        if let Err(e) = DownloadArchive::record(outcome) {
+            warn!("Failed to update the download archive: {}", e);
+        }
+    }
+
+    fn archived_outcome(
+        entry: &ArchiveEntry,
+        request: &DownloadRequest,
+        started: Instant,
+    ) -> DownloadOutcome {
+        DownloadOutcome {
+            file_size: std::fs::metadata(&entry.path).ok().map(|m| m.len()),
+            primary_file: entry.path.clone(),
+            title: entry.title.clone(),
+            video_id: Some(entry.id.clone()),
+            elapsed: started.elapsed(),
+            platform: request.platform.clone(),
+            skipped: true,
+            archived: true,
+            ..Default::default()
+        }
+    }
+
+    /// Checks whether downloading `request` would run into an existing file.
+    ///
+    /// Used to ask the user before starting; playlists are not checked.
+    pub async fn check_conflict(&self, request: &DownloadRequest) -> Result<Option<FileConflict>> {
+        let url = Self::sanitize_url(&request.url);
+        Self::validate_url(&url)?;
+
+        if Self::is_playlist_url(&url) {
+            return Ok(None);
+        }
+
+        let output_path = self.output_directory.clone();
+        let request = request.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let template = request.filename_template();
+            validate_template(template)?;
+            let (dir, cmd_template) = Self::output_location(&output_path, template);
+            let cmd_template = request.output_template(&cmd_template);
+
+            Ok(Self::predict_target(&url, &dir, &cmd_template, &request)?
+                .and_then(FileConflict::detect))
+        })
+        .await
+        .map_err(|e| DownloadError::DownloadFailed(format!("Task join error: {}", e)))?
+    }
+
+    /// Asks yt-dlp, without downloading, which file `request` would produce.
+    ///
+    /// Returns `None` for playlists, whose entries are checked one by one.
+    fn predict_target(
+        url: &str,
+        dir: &Path,
+        template: &str,
+        request: &DownloadRequest,
+    ) -> Result<Option<IncomingFile>> {
+        let mut cmd = Command::new("yt-dlp");
+        cmd.current_dir(dir)
+            .arg(url)
+            .arg("--no-playlist")
+            .arg("--playlist-items")
+            .arg("1")
+            .args(network::yt_dlp_args(url))
+            .args(auth::yt_dlp_args(&request.platform));
+
+        Self::apply_output_args(&mut cmd, dir, template);
+
+        Self::apply_request_args(&mut cmd, request, template);
+
+        let output = cmd
+            .arg("--print")
+            .arg(IncomingFile::TEMPLATE)
+            .output()
+            .map_err(|e| DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(DownloadError::from_yt_dlp_stderr(&String::from_utf8_lossy(
+                &output.stderr,
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some(mut incoming) = stdout
+            .lines()
+            .find_map(|line| serde_json::from_str::<IncomingFile>(line.trim()).ok())
+        else {
+            warn!("yt-dlp did not predict a file name for {}", url);
+            return Ok(None);
+        };
+
+        if incoming.is_playlist_entry() {
+            return Ok(None);
+        }
+
+        incoming.path = request.final_path(dir.join(&incoming.path));
+
+        Ok(Some(incoming))
+    }
+
+    /// Applies the conflict policy of `request` before anything is downloaded.
+    fn plan_target(
+        url: &str,
+        dir: &Path,
+        template: &str,
+        request: &DownloadRequest,
+        started: Instant,
+    ) -> Result<TargetPlan> {
+        let converts = request.audio.is_some() || request.remuxes_webm();
+        if !request.conflict.needs_prediction(converts) {
+            return Ok(TargetPlan::Download(template.to_string()));
+        }
+
+        let incoming = match Self::predict_target(url, dir, template, request)? {
+            Some(incoming) if incoming.path.exists() => incoming,
+            _ => return Ok(TargetPlan::Download(template.to_string())),
+        };
+
+        if request.conflict == ConflictPolicy::Rename {
+            let free = next_free_path(&incoming.path);
+            info!(
+                "{} exists, downloading as {}",
+                incoming.path.display(),
+                free.display()
+            );
+            // Keep %(ext)s so intermediate files before merging or conversion get their own names
+            let stem = free.with_extension("").to_string_lossy().replace('%', "%%");
+            return Ok(TargetPlan::Download(format!("{}.%(ext)s", stem)));
+        }
+
+        info!("{} already exists, skipping", incoming.path.display());
+        Ok(TargetPlan::Keep(Box::new(DownloadOutcome {
+            file_size: std::fs::metadata(&incoming.path).ok().map(|m| m.len()),
+            primary_file: incoming.path,
+            title: incoming.title.unwrap_or_else(|| "video".to_string()),
+            uploader: incoming.uploader,
+            video_id: incoming.id,
+            duration: incoming
+                .duration
+                .filter(|d| d.is_finite() && *d >= 0.0)
+                .map(Duration::from_secs_f64),
+            audio_codec: request
+                .audio
+                .as_ref()
+                .map(|a| a.codec.extension().to_string()),
+            elapsed: started.elapsed(),
+            platform: request.platform.clone(),
+            skipped: true,
+            ..Default::default()
+        })))
+    }
+
+    /// Maps an error of the `youtube_dl` crate onto our error variants.
+    fn classify_youtube_dl_error(error: youtube_dl::Error) -> DownloadError {
+        match error {
+            youtube_dl::Error::ExitCode { stderr, .. } => {
+                DownloadError::from_yt_dlp_stderr(&stderr)
+            }
+            youtube_dl::Error::Io(e) => {
+                DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e))
+            }
+            youtube_dl::Error::ProcessTimeout => {
+                DownloadError::NetworkError("yt-dlp timed out".to_string())
+            }
+            other => DownloadError::ExtractionError(other.to_string()),
+        }
+    }
+
+    /// Downloads the selected entries of a playlist into a folder named after it.
+    ///
+    /// Entries are fetched one by one so that a single unavailable video is
+    /// recorded as a failure instead of aborting the rest of the playlist.
+    pub async fn download_playlist<F>(
+        &self,
+        mut request: DownloadRequest,
+        cancel: CancellationToken,
+        on_progress: F,
+    ) -> Result<PlaylistDownloadResult>
+    where
+        F: Fn(PlaylistProgress) + Send + Sync + 'static,
+    {
+        info!("Starting playlist download for URL: {}", request.url);
+
+        let url = Self::sanitize_url(&request.url);
+        Self::validate_url(&url)?;
+
+        if cancel.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+
+        let warning = Self::check_sponsorblock(&url, &mut request).await;
+        let output_dir = self.output_directory.clone();
+
+        let mut result = tokio::task::spawn_blocking(move || {
+            Self::fetch_and_download_playlist(
+                &url,
+                Path::new(&output_dir),
+                &request,
+                &cancel,
+                on_progress,
+            )
+        })
+        .await
+        .map_err(|e| DownloadError::DownloadFailed(format!("Task join error: {}", e)))??;
+
+        result.warnings.extend(warning);
+        Ok(result)
+    }
+
+    fn fetch_and_download_playlist<F>(
+        url: &str,
+        base_dir: &Path,
+        request: &DownloadRequest,
+        cancel: &CancellationToken,
+        on_progress: F,
+    ) -> Result<PlaylistDownloadResult>
+    where
+        F: Fn(PlaylistProgress),
+    {
+        // Flat listing is cheap, so select entries locally by position
+        let mut listing = YoutubeDl::new(url);
+        listing.flat_playlist(true);
+        for arg in network::yt_dlp_args(url)
+            .into_iter()
+            .chain(auth::yt_dlp_args(&request.platform))
+        {
+            listing.extra_arg(arg);
+        }
+        let listing = listing.run().map_err(Self::classify_youtube_dl_error)?;
+
+        if cancel.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+
+        match listing {
+            YoutubeDlOutput::Playlist(playlist) => {
+                let title = playlist
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| "Playlist".to_string());
+                let entries = Self::playlist_entries(&playlist, Some(&request.playlist));
+                Self::download_playlist_entries(
+                    &title,
+                    entries,
+                    base_dir,
+                    request,
+                    cancel,
                     on_progress,
                 )
             }
-            Ok(YoutubeDlOutput::SingleVideo(video)) => {
-                let video_title = video.title.clone().unwrap_or_else(|| "video".to_string());
-                info!("Metadata fetched: {}", video_title);
-
-                // Perform actual download
-                info!("Executing yt-dlp with output template: {}", output_template);
-
-                // Get list of files before download to detect new file
-                let files_before: std::collections::HashSet<_> = if !is_file_path {
-                    std::fs::read_dir(output_path)
-                        .ok()
-                        .map(|entries| {
-                            entries
-                                .filter_map(|e| e.ok())
-                                .filter_map(|e| e.file_name().into_string().ok())
-                                .collect()
-                        })
-                        .unwrap_or_default()
-                } else {
-                    std::collections::HashSet::new()
-                };
+            YoutubeDlOutput::SingleVideo(_) => Err(DownloadError::DownloadFailed(
+                "URL does not point to a playlist".to_string(),
+            )),
+        }
+    }
 
-                let mut cmd = Command::new("yt-dlp");
-                cmd.current_dir(working_dir);
+    /// Heuristic for URLs that name a whole playlist rather than a single video.
+    pub fn is_playlist_url(url: &str) -> bool {
+        let url = Self::sanitize_url(url);
 
-                // For Command::arg(), we don't need %% escaping since it doesn't go through shell
-                let cmd_template = if is_file_path {
-                    output_path.to_string()
-                } else {
-                    format!("{}/%(title)s.%(ext)s", output_path)
-                };
+        if url.contains("youtube.com") {
+            return url.contains("/playlist") || (url.contains("list=") && !url.contains("v="));
+        }
 
-                info!("Passing to yt-dlp command: {}", cmd_template);
+        if url.contains("vk.com") || url.contains("vkvideo.ru") {
+            // Playlist links that also name a video are reduced to the video by sanitize_url
+            return url.contains("/playlist/");
+        }
 
-                cmd.arg(url).arg("-o").arg(&cmd_template).arg("--newline"); // Force newlines for progress parsing
+        url.contains("rutube.ru/plst/")
+    }
 
-                if overwrite {
-                    cmd.arg("--force-overwrite");
+    /// Resolves playlist entries to downloadable URLs.
+    ///
+    /// `selection` is `None` to keep every entry.
+    fn playlist_entries(
+        playlist: &Playlist,
+        selection: Option<&PlaylistSelection>,
+    ) -> Vec<PlaylistEntry> {
+        let is_youtube = playlist
+            .extractor
+            .as_deref()
+            .is_some_and(|e| e.to_lowercase().contains("youtube"));
+
+        playlist
+            .entries
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(pos, video)| {
+                let index = video
+                    .playlist_index
+                    .as_ref()
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as u32)
+                    .unwrap_or(pos as u32 + 1);
+
+                if selection.is_some_and(|s| !s.includes(index)) {
+                    return None;
                 }
 
-                cmd.stdout(Stdio::piped());
-
-                let mut child = cmd.spawn().map_err(|e| {
-                    DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e))
-                })?;
-
-                if let Some(stdout) = child.stdout.take() {
-                    let reader = BufReader::new(stdout);
-                    for line in reader.lines().map_while(std::result::Result::ok) {
-                        // Print to terminal so user sees progress there too
-                        println!("{}", line);
-
-                        // Parse progress
-                        // [download]  45.0% of 10.00MiB at 2.00MiB/s ETA 00:05
-                        if line.starts_with("[download]") && line.contains("%") {
-                            if let Some(pct_str) = line.split_whitespace().nth(1) {
-                                if let Ok(pct_val) = pct_str.trim_end_matches('%').parse::<f32>() {
-                                    on_progress(pct_val / 100.0);
-                                }
-                            }
+                // Flat YouTube listings may only carry the video id
+                let url = match video.webpage_url.clone().or_else(|| video.url.clone()) {
+                    Some(u) if u.starts_with("http") => u,
+                    Some(_) | None if is_youtube => {
+                        format!("https://www.youtube.com/watch?v={}", video.id)
+                    }
+                    _ => String::new(),
+                };
+
+                Some(PlaylistEntry {
+                    index,
+                    id: video.id.clone(),
+                    title: video.title.clone().unwrap_or_else(|| video.id.clone()),
+                    url,
+                })
+            })
+            .collect()
+    }
+
+    fn download_playlist_entries<F>(
+        title: &str,
+        entries: Vec<PlaylistEntry>,
+        base_dir: &Path,
+        request: &DownloadRequest,
+        cancel: &CancellationToken,
+        on_progress: F,
+    ) -> Result<PlaylistDownloadResult>
+    where
+        F: Fn(PlaylistProgress),
+    {
+        if entries.is_empty() {
+            return Err(DownloadError::DownloadFailed(
+                "No playlist entries match the selection".to_string(),
+            ));
+        }
+
+        let folder = base_dir.join(playlist_folder_name(title));
+        std::fs::create_dir_all(&folder).map_err(|e| {
+            DownloadError::IoError(format!("Failed to create {}: {}", folder.display(), e))
+        })?;
+
+        let total = entries.len();
+        info!(
+            "Downloading {} playlist entries into {}",
+            total,
+            folder.display()
+        );
+
+        let template = request.filename_template();
+        validate_template(template)?;
+        let template = request.output_template(template);
+        let archive = Self::load_archive(request);
+        let mut downloads = Vec::new();
+        let mut failed = Vec::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            let position = i + 1;
+
+            if cancel.is_cancelled() {
+                return Err(DownloadError::Cancelled);
+            }
+
+            info!(
+                "Playlist entry {}/{} (#{}): {}",
+                position, total, entry.index, entry.title
+            );
+            on_progress(PlaylistProgress::new(
+                position,
+                total,
+                entry,
+                DownloadProgress::default(),
+            ));
+
+            if entry.url.is_empty() {
+                warn!("Playlist entry #{} has no downloadable URL", entry.index);
+                failed.push(PlaylistEntryFailure {
+                    entry: entry.clone(),
+                    error: "No downloadable URL for this entry".to_string(),
+                });
+                continue;
+            }
+
+            let files_before = snapshot_partial_files(&folder);
+            let started = Instant::now();
+
+            if let Some(found) = archive
+                .as_ref()
+                .and_then(|a| a.get_on_disk(&request.platform, &entry.id))
+            {
+                info!("Playlist entry #{} was downloaded before", entry.index);
+                downloads.push(Self::archived_outcome(found, request, started));
+                continue;
+            }
+
+            let entry_template =
+                match Self::plan_target(&entry.url, &folder, &template, request, started) {
+                    Ok(TargetPlan::Download(template)) => template,
+                    Ok(TargetPlan::Keep(outcome)) => {
+                        downloads.push(*outcome);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Playlist entry #{} failed: {}", entry.index, e);
+                        failed.push(PlaylistEntryFailure {
+                            entry: entry.clone(),
+                            error: e.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+            let mut cmd = Command::new("yt-dlp");
+            cmd.current_dir(&folder)
+                .arg(&entry.url)
+                .arg("--no-playlist")
+                .args(network::yt_dlp_args(&entry.url))
+                .args(auth::yt_dlp_args(&request.platform));
+
+            Self::apply_output_args(&mut cmd, &folder, &entry_template);
+
+            Self::apply_request_args(&mut cmd, request, &entry_template);
+
+            let entry_progress =
+                |p: DownloadProgress| on_progress(PlaylistProgress::new(position, total, entry, p));
+
+            match Self::run_download_command(
+                cmd,
+                request.rate_limit,
+                &folder,
+                &files_before,
+                cancel,
+                &entry_progress,
+                false,
+                None,
+            ) {
+                Ok(CommandRun::Finished { media, files }) => {
+                    let media = media.unwrap_or_default();
+                    match Self::build_outcome(media, files, request, started) {
+                        Ok(outcome) => {
+                            Self::archive_download(request, &outcome);
+                            downloads.push(outcome);
+                        }
+                        Err(e) => {
+                            warn!("Playlist entry #{} failed: {}", entry.index, e);
+                            failed.push(PlaylistEntryFailure {
+                                entry: entry.clone(),
+                                error: e.to_string(),
+                            });
                         }
                     }
                 }
+                // Entries run with --no-playlist and are looked up beforehand
+                Ok(CommandRun::PlaylistDetected(_)) | Ok(CommandRun::Archived(_)) => {}
+                Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+                Err(e) => {
+                    warn!("Playlist entry #{} failed: {}", entry.index, e);
+                    failed.push(PlaylistEntryFailure {
+                        entry: entry.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
 
-                let status = child.wait().map_err(|e| {
-                    DownloadError::IoError(format!("Failed to wait for yt-dlp: {}", e))
-                })?;
+        if downloads.is_empty() && !failed.is_empty() {
+            return Err(DownloadError::DownloadFailed(format!(
+                "All {} playlist entries failed: {}",
+                failed.len(),
+                failed[0].error
+            )));
+        }
 
-                if !status.success() {
-                    return Err(DownloadError::DownloadFailed(
-                        "Download process failed".to_string(),
-                    ));
-                }
+        info!(
+            "Playlist finished: {} file(s), {} failed entr(ies)",
+            downloads.len(),
+            failed.len()
+        );
 
-                info!("Download completed: {}", video_title);
-
-                // Detect the actual downloaded file by comparing directory contents
-                if is_file_path {
-                    Ok(output_path.to_string())
-                } else {
-                    // Find newly created file
-                    let files_after: std::collections::HashSet<_> = std::fs::read_dir(output_path)
-                        .ok()
-                        .map(|entries| {
-                            entries
-                                .filter_map(|e| e.ok())
-                                .filter_map(|e| e.file_name().into_string().ok())
-                                .collect()
-                        })
-                        .unwrap_or_default();
-
-                    let new_files: Vec<_> = files_after.difference(&files_before).collect();
-
-                    if let Some(filename) = new_files.first() {
-                        let full_path = format!("{}/{}", output_path, filename);
-                        info!("Detected downloaded file: {}", full_path);
-                        Ok(full_path)
-                    } else {
-                        // Fallback to constructed path
-                        let ext = video.ext.clone().unwrap_or("mp4".to_string());
-                        Ok(format!("{}/{}.{}", output_path, video_title, ext))
-                    }
+        Ok(PlaylistDownloadResult {
+            title: title.to_string(),
+            folder,
+            downloads,
+            failed,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Splits the requested output into a directory and a yt-dlp template for it.
+    ///
+    /// An existing directory, or a path without an extension, receives files
+    /// named by `template`. Anything else is taken as the wanted file name, whose
+    /// extension yt-dlp still decides so that merging and conversion stay correct.
+    fn output_location(output_path: &str, template: &str) -> (PathBuf, String) {
+        let path = Path::new(output_path);
+        if path.is_dir() || path.extension().is_none() {
+            return (path.to_path_buf(), template.to_string());
+        }
+
+        let dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().replace('%', "%%"))
+            .unwrap_or_else(|| "%(title)s".to_string());
+
+        (dir.to_path_buf(), format!("{}.%(ext)s", stem))
+    }
+
+    /// Points yt-dlp at the output directory and template, keeping names portable.
+    fn apply_output_args(cmd: &mut Command, dir: &Path, template: &str) {
+        cmd.arg("-P")
+            .arg(dir)
+            .arg("-o")
+            .arg(template)
+            // Replaces characters Windows and most filesystems reject
+            .arg("--windows-filenames")
+            .arg("--trim-filenames")
+            .arg(TRIM_FILENAME_CHARS.to_string());
+    }
+
+    /// Adds the options chosen in the request to a yt-dlp download command
+    /// writing to `template`.
+    /// Base yt-dlp command for a URL that `is_playlist_url` does not treat as a playlist.
+    ///
+    /// `--no-playlist` keeps a watch URL carrying `list=` (such as a mix) to
+    /// the one video; a bare playlist URL is still reported as one.
+    fn single_video_command(url: &str, working_dir: &Path, request: &DownloadRequest) -> Command {
+        let mut cmd = Command::new("yt-dlp");
+        cmd.current_dir(working_dir)
+            .arg(url)
+            .arg("--no-playlist")
+            .args(network::yt_dlp_args(url))
+            .args(auth::yt_dlp_args(&request.platform));
+        cmd
+    }
+
+    fn apply_request_args(cmd: &mut Command, request: &DownloadRequest, template: &str) {
+        cmd.args(request.conflict.yt_dlp_args());
+        if request.resume {
+            cmd.arg("--continue");
+        }
+        cmd.args(
+            request
+                .subtitles
+                .yt_dlp_args(&request.format, request.audio.as_ref()),
+        );
+        cmd.args(request.clip.yt_dlp_args());
+        if let Some(split) = request.split_chapters {
+            cmd.args(split.yt_dlp_args(template));
+        }
+        cmd.args(request.sponsorblock.yt_dlp_args());
+        if let Some(rate) = request.throttled_rate {
+            cmd.arg("--throttled-rate").arg(rate.to_string());
+        }
+        cmd.args(
+            request
+                .embed
+                .yt_dlp_args(&request.format, request.audio.as_ref()),
+        );
+        if request.remuxes_webm() {
+            // Merged YouTube streams often end up as webm, which holds neither cover art nor SRT
+            cmd.arg("--remux-video").arg("webm>mkv");
+        }
+
+        if let Some(selector) = request.format_selector() {
+            info!("Using format selector: {}", selector);
+            cmd.arg("-f").arg(selector);
+        }
+
+        match request.audio {
+            Some(ref audio) => {
+                info!("Extracting audio as {}", audio.codec.extension());
+                cmd.args(audio.yt_dlp_args());
+            }
+            None => {
+                if let Some(container) = request.format.merge_output_format() {
+                    cmd.arg("--merge-output-format").arg(container);
                 }
             }
-            Err(e) => {
-                let error_msg = e.to_string();
-                if error_msg.contains("Video unavailable") || error_msg.contains("not found") {
-                    warn!("Video not found or unavailable: {}", e);
-                    Err(DownloadError::DownloadFailed(
-                        "Video not found or unavailable".to_string(),
-                    ))
-                } else if error_msg.contains("Network") || error_msg.contains("Connection") {
-                    warn!("Network error: {}", e);
-                    Err(DownloadError::DownloadFailed(error_msg))
-                } else if error_msg.contains("KeyError")
-                    && error_msg.contains("exportResponse")
-                    && url.contains("dzen.ru")
-                {
-                    warn!(
-                        "Dzen article/channel URL detected (extractor broken): {}",
-                        e
+        }
+    }
+
+    /// Runs a prepared yt-dlp command, forwarding progress and honouring cancellation.
+    ///
+    /// Returns the metadata and final file paths yt-dlp printed. With
+    /// `detect_playlist` the process is stopped as soon as yt-dlp starts on a
+    /// playlist entry, so the caller can switch to per-entry downloads. On cancel
+    /// the whole process tree is killed and any partial files created since
+    /// `files_before` was taken are removed from under `working_dir`.
+    ///
+    /// The speed is capped at this download's share of the global limit, within
+    /// `rate_limit`. When the share changes mid-download yt-dlp is restarted
+    /// with the new cap and resumes its partial files, see `resume_command`.
+    #[allow(clippy::too_many_arguments)]
+    fn run_download_command<F>(
+        cmd: Command,
+        rate_limit: Option<u64>,
+        working_dir: &Path,
+        files_before: &HashSet<PathBuf>,
+        cancel: &CancellationToken,
+        on_progress: &F,
+        detect_playlist: bool,
+        archive: Option<(&DownloadArchive, &Platform)>,
+    ) -> Result<CommandRun>
+    where
+        F: Fn(DownloadProgress),
+    {
+        let slot = RateSlot::acquire(rate_limit);
+        on_progress(DownloadProgress::stage(DownloadStage::Extracting));
+
+        let mut restarted = false;
+        loop {
+            let rate = slot.rate();
+            let mut cmd = if restarted {
+                resume_command(&cmd)
+            } else {
+                copy_command(&cmd)
+            };
+            if let Some(rate) = rate {
+                cmd.arg("--limit-rate").arg(rate.to_string());
+            }
+
+            match Self::run_download_attempt(
+                cmd,
+                working_dir,
+                files_before,
+                cancel,
+                on_progress,
+                detect_playlist,
+                archive,
+                || slot.rate() != rate,
+            )? {
+                Some(run) => return Ok(run),
+                None => {
+                    info!(
+                        "Speed limit changed to {}, restarting yt-dlp",
+                        slot.rate()
+                            .map(format_rate)
+                            .unwrap_or_else(|| "unlimited".to_string())
                     );
-                    Err(DownloadError::DownloadFailed(
-                        "Dzen article/channel URLs are currently not supported by yt-dlp. Please use direct video URLs (dzen.ru/video/watch/...)".to_string(),
-                    ))
-                } else {
-                    warn!("Download error: {}", e);
-                    Err(DownloadError::DownloadFailed(error_msg))
+                    restarted = true;
                 }
             }
         }
     }
 
-    fn handle_playlist_download<F>(
-        url: &str,
-        output_path: &str,
-        is_file_path: bool,
-        overwrite: bool,
-        on_progress: F,
-    ) -> Result<String>
+    /// One yt-dlp process of `run_download_command`.
+    ///
+    /// Returns `None` when the process was stopped mid-download because
+    /// `rate_changed` reported a different speed limit.
+    #[allow(clippy::too_many_arguments)]
+    fn run_download_attempt<F, R>(
+        mut cmd: Command,
+        working_dir: &Path,
+        files_before: &HashSet<PathBuf>,
+        cancel: &CancellationToken,
+        on_progress: &F,
+        detect_playlist: bool,
+        archive: Option<(&DownloadArchive, &Platform)>,
+        rate_changed: R,
+    ) -> Result<Option<CommandRun>>
     where
-        F: Fn(f32) + Send + Sync + 'static,
+        F: Fn(DownloadProgress),
+        R: Fn() -> bool,
     {
-        // Determine working directory for temp files
-        let working_dir = if is_file_path {
-            Path::new(output_path).parent().unwrap_or(Path::new("."))
-        } else {
-            Path::new(output_path)
-        };
+        // --print implies --quiet and --simulate, so ask for output and the download explicitly
+        // Conversion and merging rename files, so yt-dlp reports where they ended up
+        cmd.arg("--no-simulate")
+            .arg("--print")
+            .arg(format!(
+                "pre_process:{}{}",
+                MEDIA_INFO_MARKER,
+                MediaInfo::TEMPLATE
+            ))
+            .arg("--print")
+            .arg(format!(
+                "after_move:{}{}",
+                FINAL_PATH_MARKER,
+                DownloadedFiles::TEMPLATE
+            ));
+        cmd.args(DownloadProgress::yt_dlp_args());
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
-        let output_template = if is_file_path {
-            output_path.to_string()
-        } else {
-            format!("{}/%%(title)s.%%(ext)s", output_path)
-        };
+        // Own process group so cancellation also reaches ffmpeg children
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
 
-        info!(
-            "Using output template for playlist download: {}",
-            output_template
-        );
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e)))?;
 
-        // The youtube_dl crate needs the template without %% escaping
-        let crate_template = if is_file_path {
-            output_path.to_string()
-        } else {
-            format!("{}/%(title)s.%(ext)s", output_path)
-        };
+        let watcher = CancelWatcher::spawn(child.id(), cancel.clone());
 
-        match YoutubeDl::new(url)
-            .socket_timeout("30")
-            .extract_audio(false)
-            .output_template(&crate_template)
-            .playlist_items(1u32)
-            .run()
-        {
-            Ok(YoutubeDlOutput::Playlist(playlist)) => {
-                if let Some(entries) = playlist.entries {
-                    if let Some(video) = entries.first() {
-                        // Get list of files before download
-                        let files_before: std::collections::HashSet<_> = if !is_file_path {
-                            std::fs::read_dir(output_path)
-                                .ok()
-                                .map(|entries| {
-                                    entries
-                                        .filter_map(|e| e.ok())
-                                        .filter_map(|e| e.file_name().into_string().ok())
-                                        .collect()
-                                })
-                                .unwrap_or_default()
-                        } else {
-                            std::collections::HashSet::new()
-                        };
-
-                        // Perform actual download for the first item
-                        let mut cmd = Command::new("yt-dlp");
-                        cmd.current_dir(working_dir);
-
-                        // For Command::arg(), we don't need %% escaping since it doesn't go through shell
-                        let cmd_template = if is_file_path {
-                            output_path.to_string()
-                        } else {
-                            format!("{}/%(title)s.%(ext)s", output_path)
-                        };
-
-                        info!("Passing to yt-dlp command (playlist): {}", cmd_template);
-
-                        cmd.arg(url)
-                            .arg("-o")
-                            .arg(&cmd_template)
-                            .arg("--playlist-items")
-                            .arg("1")
-                            .arg("--newline"); // Force newlines for progress parsing
-
-                        if overwrite {
-                            cmd.arg("--force-overwrite");
+        // Drain stderr on its own thread so a chatty yt-dlp cannot block on a full pipe
+        let stderr_reader = child.stderr.take().map(|stderr| {
+            std::thread::spawn(move || {
+                let mut collected = String::new();
+                for line in BufReader::new(stderr)
+                    .lines()
+                    .map_while(std::result::Result::ok)
+                {
+                    debug!("yt-dlp: {}", line);
+                    collected.push_str(&line);
+                    collected.push('\n');
+                }
+                collected
+            })
+        });
+        let mut reported_files = Vec::new();
+        let mut media = None;
+        let mut playlist = None;
+        let mut archived = None;
+        let mut restart = false;
+
+        if let Some(stdout) = child.stdout.take() {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().map_while(std::result::Result::ok) {
+                // Print to terminal so user sees progress there too
+                println!("{}", line);
+
+                if let Some(json) = line.strip_prefix(FINAL_PATH_MARKER) {
+                    match DownloadedFiles::from_report(json.trim(), working_dir) {
+                        Some(mut files) => {
+                            // Embedded thumbnails and the like are deleted after use
+                            files.side_files.retain(|f| f.exists());
+                            // Embedded subtitles are gone too, only their languages remain
+                            files.subtitles.retain(|f| f.exists());
+                            reported_files.push(files);
                         }
+                        None => warn!("Ignoring unreadable file report: {}", json),
+                    }
+                    continue;
+                }
 
-                        cmd.stdout(Stdio::piped());
-
-                        let mut child = cmd.spawn().map_err(|e| {
-                            DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e))
-                        })?;
-
-                        if let Some(stdout) = child.stdout.take() {
-                            let reader = BufReader::new(stdout);
-                            for line in reader.lines().map_while(std::result::Result::ok) {
-                                // Print to terminal
-                                println!("{}", line);
-
-                                if line.starts_with("[download]") && line.contains("%") {
-                                    if let Some(pct_str) = line.split_whitespace().nth(1) {
-                                        if let Ok(pct_val) =
-                                            pct_str.trim_end_matches('%').parse::<f32>()
-                                        {
-                                            on_progress(pct_val / 100.0);
-                                        }
-                                    }
-                                }
+                if let Some(json) = line.strip_prefix(MEDIA_INFO_MARKER) {
+                    match serde_json::from_str::<MediaInfo>(json.trim()) {
+                        Ok(info) if detect_playlist && info.is_playlist_entry() => {
+                            info!("URL is a playlist, stopping the single download");
+                            terminate_process_tree(child.id());
+                            playlist = Some(info);
+                            break;
+                        }
+                        Ok(info) => {
+                            let found = archive.and_then(|(archive, platform)| {
+                                archive.get_on_disk(platform, info.id.as_deref()?)
+                            });
+                            if let Some(entry) = found {
+                                info!("Already downloaded, stopping: {}", entry.path.display());
+                                terminate_process_tree(child.id());
+                                archived = Some(entry.clone());
+                                break;
                             }
+                            media = Some(info);
                         }
+                        Err(e) => debug!("Ignoring unreadable metadata line: {}", e),
+                    }
+                    continue;
+                }
 
-                        let status = child.wait().map_err(|e| {
-                            DownloadError::IoError(format!("Failed to wait for yt-dlp: {}", e))
-                        })?;
-
-                        if !status.success() {
-                            return Err(DownloadError::DownloadFailed(
-                                "Download process failed".to_string(),
-                            ));
-                        }
+                if let Some(progress) = DownloadProgress::parse_line(&line) {
+                    // Only restart while bytes are still coming, never during post-processing
+                    let unfinished = match (progress.downloaded_bytes, progress.total_bytes) {
+                        (Some(done), Some(total)) => done < total,
+                        _ => true,
+                    };
+                    let downloading = matches!(
+                        progress.stage,
+                        DownloadStage::Downloading
+                            | DownloadStage::DownloadingVideo
+                            | DownloadStage::DownloadingAudio
+                    );
+                    on_progress(progress);
 
-                        // Detect the actual downloaded file by comparing directory contents
-                        if is_file_path {
-                            Ok(output_path.to_string())
-                        } else {
-                            // Find newly created file
-                            let files_after: std::collections::HashSet<_> =
-                                std::fs::read_dir(output_path)
-                                    .ok()
-                                    .map(|entries| {
-                                        entries
-                                            .filter_map(|e| e.ok())
-                                            .filter_map(|e| e.file_name().into_string().ok())
-                                            .collect()
-                                    })
-                                    .unwrap_or_default();
-
-                            let new_files: Vec<_> = files_after.difference(&files_before).collect();
-
-                            if let Some(filename) = new_files.first() {
-                                let full_path = format!("{}/{}", output_path, filename);
-                                info!("Detected downloaded file from playlist: {}", full_path);
-                                Ok(full_path)
-                            } else {
-                                // Fallback to constructed path
-                                let title = video.title.clone().unwrap_or("video".to_string());
-                                let ext = video.ext.clone().unwrap_or("mp4".to_string());
-                                Ok(format!("{}/{}.{}", output_path, title, ext))
-                            }
-                        }
-                    } else {
-                        Err(DownloadError::DownloadFailed("Empty playlist".to_string()))
+                    if downloading && unfinished && rate_changed() {
+                        terminate_process_tree(child.id());
+                        restart = true;
+                        break;
                     }
-                } else {
-                    Err(DownloadError::DownloadFailed(
-                        "Failed to get playlist entries".to_string(),
-                    ))
                 }
             }
-            Ok(YoutubeDlOutput::SingleVideo(video)) => {
-                // Should not happen if playlist_items is used, but handle it just in case
-                let video_title = video.title.clone().unwrap_or_else(|| "video".to_string());
-                let ext = video.ext.clone().unwrap_or("mp4".to_string());
-
-                let status = std::process::Command::new("yt-dlp")
-                    .arg(url)
-                    .arg("-o")
-                    .arg(&output_template)
-                    .status()
-                    .map_err(|e| {
-                        DownloadError::IoError(format!("Failed to execute yt-dlp: {}", e))
-                    })?;
+        }
 
-                if !status.success() {
-                    return Err(DownloadError::DownloadFailed(
-                        "Download process failed".to_string(),
-                    ));
-                }
+        let status = child
+            .wait()
+            .map_err(|e| DownloadError::IoError(format!("Failed to wait for yt-dlp: {}", e)))?;
+        drop(watcher);
+        let stderr = stderr_reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+
+        if let Some(info) = playlist {
+            cleanup_partial_files(working_dir, files_before);
+            return Ok(Some(CommandRun::PlaylistDetected(info)));
+        }
 
-                if is_file_path {
-                    Ok(output_path.to_string())
-                } else {
-                    Ok(format!("{}/{}.{}", output_path, video_title, ext))
-                }
+        if let Some(entry) = archived {
+            cleanup_partial_files(working_dir, files_before);
+            return Ok(Some(CommandRun::Archived(entry)));
+        }
+
+        if cancel.is_cancelled() {
+            if cancel.keeps_partial_files() {
+                info!("Download interrupted, keeping partial files to resume");
+            } else {
+                info!("Download cancelled, cleaning up partial files");
+                cleanup_partial_files(working_dir, files_before);
             }
-            Err(e) => Err(DownloadError::DownloadFailed(e.to_string())),
+            return Err(DownloadError::Cancelled);
         }
+
+        // Partial files stay for the next attempt to resume
+        if restart {
+            return Ok(None);
+        }
+
+        if !status.success() {
+            let error = DownloadError::from_yt_dlp_stderr(&stderr);
+            warn!("yt-dlp failed: {}", error);
+            return Err(error);
+        }
+
+        Ok(Some(CommandRun::Finished {
+            media,
+            files: reported_files,
+        }))
     }
 
     #[allow(dead_code)]
@@ -559,9 +1566,44 @@ impl VideoDownloader {
     }
 }
 
+/// A fresh copy of a prepared command, since `Command` cannot be cloned or spawned twice.
+fn copy_command(cmd: &Command) -> Command {
+    copy_command_with_args(cmd, cmd.get_args())
+}
+
+fn copy_command_with_args<'a>(
+    cmd: &Command,
+    args: impl IntoIterator<Item = &'a std::ffi::OsStr>,
+) -> Command {
+    let mut copy = Command::new(cmd.get_program());
+    copy.args(args);
+    if let Some(dir) = cmd.get_current_dir() {
+        copy.current_dir(dir);
+    }
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => copy.env(key, value),
+            None => copy.env_remove(key),
+        };
+    }
+    copy
+}
+
+/// A copy of `cmd` for restarting it mid-download, continuing its partial files.
+///
+/// `--force-overwrites` implies `--no-continue`, so it is dropped: the first run
+/// already replaced the old file, and keeping it would start from zero each time.
+fn resume_command(cmd: &Command) -> Command {
+    let args = cmd.get_args().filter(|arg| *arg != "--force-overwrites");
+    let mut resume = copy_command_with_args(cmd, args);
+    resume.arg("--continue");
+    resume
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::format::{AudioCodec, Container};
 
     #[test]
     fn test_detect_platform_youtube() {
@@ -636,6 +1678,151 @@ mod tests {
         assert_eq!(sanitized_no_video, url_no_video);
     }
 
+    #[test]
+    fn test_audio_request_args() {
+        let request = DownloadRequest {
+            url: "https://www.youtube.com/watch?v=abc123".to_string(),
+            audio: Some(AudioExtraction::new(AudioCodec::Opus)),
+            format: FormatSpec {
+                container: Some(Container::Mkv),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut cmd = Command::new("yt-dlp");
+        VideoDownloader::apply_request_args(&mut cmd, &request, DEFAULT_TEMPLATE);
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+
+        assert!(args.windows(2).any(|w| w == ["-f", "ba/b"]));
+        assert!(args.windows(2).any(|w| w == ["--audio-format", "opus"]));
+        assert!(args.contains(&"--embed-thumbnail".to_string()));
+        assert!(!args.contains(&"--merge-output-format".to_string()));
+    }
+
+    #[test]
+    fn test_downloaded_files_from_report() {
+        let json = r#"{"filepath": "/music/Song.opus",
+            "requested_subtitles": {"en": {"ext": "vtt", "filepath": "/music/Song.en.vtt"}},
+            "thumbnails": [{"url": "https://i.ytimg.com/a.jpg"}, {"id": "1", "filepath": "Song.jpg"}],
+            "__infojson_filename": "/music/Song.info.json"}"#;
+        let files = DownloadedFiles::from_report(json, Path::new("/music")).unwrap();
+
+        assert_eq!(files.path, PathBuf::from("/music/Song.opus"));
+        assert_eq!(
+            files.side_files,
+            vec![
+                PathBuf::from("/music/Song.en.vtt"),
+                PathBuf::from("/music/Song.info.json"),
+                PathBuf::from("/music/Song.jpg"),
+            ]
+        );
+
+        assert!(!files.skipped);
+        assert_eq!(files.subtitles, vec![PathBuf::from("/music/Song.en.vtt")]);
+        assert_eq!(files.subtitle_languages, ["en"]);
+
+        let kept = DownloadedFiles::from_report(
+            r#"{"filepath": "Song.opus", "__real_download": false}"#,
+            Path::new("/music"),
+        )
+        .unwrap();
+        assert!(kept.skipped);
+
+        assert!(DownloadedFiles::from_report(r#"{"thumbnails": null}"#, Path::new(".")).is_none());
+        assert!(DownloadedFiles::from_report("NA", Path::new(".")).is_none());
+    }
+
+    #[test]
+    fn test_media_info_playlist_detection() {
+        let single: MediaInfo =
+            serde_json::from_str(r#"{"title": "Clip", "ext": "mp4", "playlist_id": null}"#)
+                .unwrap();
+        assert!(!single.is_playlist_entry());
+        assert_eq!(single.title.as_deref(), Some("Clip"));
+
+        let entry: MediaInfo = serde_json::from_str(
+            r#"{"title": "Clip", "playlist_id": "PL123", "playlist_title": "Mix"}"#,
+        )
+        .unwrap();
+        assert!(entry.is_playlist_entry());
+    }
+
+    #[test]
+    fn test_output_location() {
+        let dir = std::env::temp_dir();
+        let template = "%(uploader)s/%(title)s.%(ext)s";
+
+        assert_eq!(
+            VideoDownloader::output_location(dir.to_str().unwrap(), template),
+            (dir.clone(), template.to_string())
+        );
+        assert_eq!(
+            VideoDownloader::output_location("/videos/100% clip.mp4", template),
+            (PathBuf::from("/videos"), "100%% clip.%(ext)s".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_playlist_url() {
+        assert!(VideoDownloader::is_playlist_url(
+            "https://www.youtube.com/playlist?list=PL123"
+        ));
+        assert!(!VideoDownloader::is_playlist_url(
+            "https://www.youtube.com/watch?v=abc123&list=PL123"
+        ));
+        assert!(VideoDownloader::is_playlist_url(
+            "https://vkvideo.ru/playlist/-220754053_3"
+        ));
+        assert!(!VideoDownloader::is_playlist_url(
+            "https://vkvideo.ru/playlist/-220754053_3/video-220754053_456244420"
+        ));
+        assert!(VideoDownloader::is_playlist_url(
+            "https://rutube.ru/plst/123456/"
+        ));
+    }
+
+    #[test]
+    fn test_watch_url_with_list_downloads_one_video() {
+        let url = "https://www.youtube.com/watch?v=abc123&list=RDabc123&start_radio=1";
+        assert!(!VideoDownloader::is_playlist_url(url));
+
+        let request = DownloadRequest {
+            url: url.to_string(),
+            platform: Platform::YouTube,
+            ..Default::default()
+        };
+        let cmd = VideoDownloader::single_video_command(url, Path::new("."), &request);
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        assert!(args.contains(&"--no-playlist".to_string()));
+    }
+
+    #[test]
+    fn test_playlist_entries_selection() {
+        let json = r#"{"_type":"playlist","id":"PL1","title":"Lectures","extractor":"youtube:tab","entries":[
+            {"id":"aaa","title":"One","url":"aaa"},
+            {"id":"bbb","title":"Two","url":"https://www.youtube.com/watch?v=bbb"},
+            {"id":"ccc","title":"Three","url":"https://www.youtube.com/watch?v=ccc"}
+        ]}"#;
+        let playlist: Playlist = serde_json::from_str(json).unwrap();
+
+        let all = VideoDownloader::playlist_entries(&playlist, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].url, "https://www.youtube.com/watch?v=aaa");
+        assert_eq!(all[2].index, 3);
+
+        let selection = PlaylistSelection::Items(vec![(1, 1), (3, 3)]);
+        let picked = VideoDownloader::playlist_entries(&playlist, Some(&selection));
+        let titles: Vec<&str> = picked.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["One", "Three"]);
+    }
+
     #[test]
     fn test_sanitize_url_dzen() {
         let url =
@@ -650,4 +1837,72 @@ mod tests {
         let sanitized_clean = VideoDownloader::sanitize_url(url_clean);
         assert_eq!(sanitized_clean, url_clean);
     }
+
+    #[test]
+    fn test_restart_continues_partial_files() {
+        let request = DownloadRequest {
+            conflict: ConflictPolicy::Overwrite,
+            ..Default::default()
+        };
+        let mut cmd = Command::new("yt-dlp");
+        VideoDownloader::apply_request_args(&mut cmd, &request, DEFAULT_TEMPLATE);
+        assert!(cmd.get_args().any(|arg| arg == "--force-overwrites"));
+
+        let args: Vec<String> = resume_command(&cmd)
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert!(!args.contains(&"--force-overwrites".to_string()));
+        assert_eq!(args.last().map(String::as_str), Some("--continue"));
+    }
+
+    #[test]
+    fn test_final_path_follows_remux() {
+        let mut request = DownloadRequest::default();
+        let webm = PathBuf::from("/videos/Talk.webm");
+        assert_eq!(request.final_path(webm.clone()), webm);
+
+        request.embed.thumbnail = true;
+        assert_eq!(
+            request.final_path(webm.clone()),
+            PathBuf::from("/videos/Talk.mkv")
+        );
+        assert_eq!(
+            request.final_path(PathBuf::from("/videos/Talk.mp4")),
+            PathBuf::from("/videos/Talk.mp4")
+        );
+
+        request.audio = Some(AudioExtraction::new(AudioCodec::Mp3));
+        assert_eq!(request.final_path(webm), PathBuf::from("/videos/Talk.mp3"));
+    }
+
+    #[test]
+    fn test_embedded_srt_remuxes_webm() {
+        let request = DownloadRequest {
+            subtitles: SubtitleOptions {
+                languages: vec!["en".to_string()],
+                embed: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            request.final_path(PathBuf::from("/videos/Talk.webm")),
+            PathBuf::from("/videos/Talk.mkv")
+        );
+
+        let mut cmd = Command::new("yt-dlp");
+        VideoDownloader::apply_request_args(&mut cmd, &request, DEFAULT_TEMPLATE);
+        let args: Vec<String> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+        assert!(args.windows(2).any(|w| w == ["--remux-video", "webm>mkv"]));
+        assert_eq!(
+            args.iter()
+                .filter(|a| a.as_str() == "--remux-video")
+                .count(),
+            1
+        );
+    }
 }
@@ -0,0 +1,119 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  embed.rs                                             TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 12 2025 13:05 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 12 2025 13:05 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::format::{AudioExtraction, Container, FormatSpec};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// What to write into a downloaded video file once it is complete; nothing by default.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct EmbedOptions {
+    /// Title, uploader, upload date, description and source URL as container tags
+    pub metadata: bool,
+    /// The video thumbnail as cover art
+    pub thumbnail: bool,
+    /// Chapter markers reported by the site
+    pub chapters: bool,
+}
+
+impl EmbedOptions {
+    /// Whether a webm download is remuxed to mkv so it can hold cover art.
+    ///
+    /// Only happens when the thumbnail is embedded and no container was chosen.
+    pub fn remuxes_webm(&self, format: &FormatSpec, audio: Option<&AudioExtraction>) -> bool {
+        self.thumbnail && audio.is_none() && format.container.is_none()
+    }
+
+    /// yt-dlp options embedding the chosen data.
+    ///
+    /// Audio extraction carries its own tag and cover settings, so only
+    /// chapters are added on top of those.
+    pub fn yt_dlp_args(&self, format: &FormatSpec, audio: Option<&AudioExtraction>) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if audio.is_none() {
+            if self.metadata {
+                // Also maps description and webpage_url into the comment/purl tags
+                args.push("--embed-metadata".to_string());
+            }
+
+            if self.thumbnail {
+                match format.container {
+                    Some(Container::Webm) => {
+                        warn!("WebM cannot hold cover art, not embedding the thumbnail")
+                    }
+                    // A webm without a chosen container is remuxed by the request
+                    _ => {
+                        args.extend([
+                            "--embed-thumbnail".to_string(),
+                            // mp4 rejects webp covers
+                            "--convert-thumbnails".to_string(),
+                            "jpg".to_string(),
+                        ]);
+                    }
+                }
+            }
+        }
+
+        if self.chapters {
+            args.push("--embed-chapters".to_string());
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::format::AudioCodec;
+
+    #[test]
+    fn test_embed_args() {
+        assert!(EmbedOptions::default()
+            .yt_dlp_args(&FormatSpec::default(), None)
+            .is_empty());
+
+        let all = EmbedOptions {
+            metadata: true,
+            thumbnail: true,
+            chapters: true,
+        };
+        assert!(all.remuxes_webm(&FormatSpec::default(), None));
+
+        let args = all.yt_dlp_args(&FormatSpec::default(), None);
+        assert!(args.contains(&"--embed-metadata".to_string()));
+        assert!(args.contains(&"--embed-chapters".to_string()));
+
+        let webm = FormatSpec {
+            container: Some(Container::Webm),
+            ..Default::default()
+        };
+        assert!(!all
+            .yt_dlp_args(&webm, None)
+            .contains(&"--embed-thumbnail".to_string()));
+
+        let audio = AudioExtraction::new(AudioCodec::Mp3);
+        assert_eq!(
+            all.yt_dlp_args(&FormatSpec::default(), Some(&audio)),
+            vec!["--embed-chapters"]
+        );
+
+        let mp4 = FormatSpec {
+            container: Some(Container::Mp4),
+            ..Default::default()
+        };
+        assert!(!all.remuxes_webm(&mp4, None));
+    }
+}
@@ -34,8 +34,44 @@ pub enum DownloadError {
     #[error("Network error: {0}")]
     NetworkError(String),
 
-    #[error("Video not found or unavailable")]
-    VideoNotFound,
+    #[error("Video not found or unavailable: {0}")]
+    VideoNotFound(String),
+
+    #[error("Video is not available in your region: {0}")]
+    GeoBlocked(String),
+
+    #[error("Video is age-restricted: {0}")]
+    AgeRestricted(String),
+
+    #[error("Video is private: {0}")]
+    PrivateVideo(String),
+
+    #[error("Login required: {0}")]
+    LoginRequired(String),
+
+    #[error("Rate limited by the site: {0}")]
+    RateLimited(String),
+
+    #[error("ffmpeg is missing: {0}")]
+    FfmpegMissing(String),
+
+    #[error("Invalid playlist selection: {0}")]
+    InvalidPlaylistSelection(String),
+
+    #[error("Invalid time range: {0}")]
+    InvalidTimeRange(String),
+
+    #[error("Invalid filename template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Invalid speed limit: {0}")]
+    InvalidRate(String),
+
+    #[error("Invalid network setting: {0}")]
+    InvalidNetworkSetting(String),
+
+    #[error("Invalid cookies file: {0}")]
+    InvalidCookies(String),
 
     #[error("Output directory does not exist or is not writable")]
     InvalidOutputDirectory,
@@ -44,4 +80,173 @@ pub enum DownloadError {
     Cancelled,
 }
 
+impl DownloadError {
+    /// Classifies yt-dlp's stderr, keeping the line that explains the failure.
+    ///
+    /// The last `ERROR:` line is used when there is one, otherwise the last
+    /// non-empty line, since yt-dlp reports the fatal problem at the end.
+    pub fn from_yt_dlp_stderr(stderr: &str) -> Self {
+        let lines: Vec<&str> = stderr
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        let line = lines
+            .iter()
+            .rev()
+            .find(|l| l.starts_with("ERROR:"))
+            .or_else(|| lines.last())
+            .map(|l| l.to_string());
+
+        let Some(line) = line else {
+            return DownloadError::DownloadFailed("yt-dlp exited without an error message".into());
+        };
+
+        let lower = line.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if has(&[
+            "ffmpeg not found",
+            "ffprobe not found",
+            "ffmpeg is not installed",
+        ]) {
+            DownloadError::FfmpegMissing(line)
+        } else if has(&[
+            "http error 429",
+            "too many requests",
+            "rate limit",
+            "rate-limit",
+        ]) {
+            DownloadError::RateLimited(line)
+        } else if has(&[
+            "available in your country",
+            "not available from your location",
+            "blocked it in your country",
+            "geo restrict",
+            "geo-restrict",
+        ]) {
+            DownloadError::GeoBlocked(line)
+        } else if has(&[
+            "confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            DownloadError::AgeRestricted(line)
+        } else if has(&[
+            "private video",
+            "video is private",
+            "this playlist is private",
+        ]) {
+            DownloadError::PrivateVideo(line)
+        } else if has(&[
+            "login required",
+            "sign in to",
+            "requires authentication",
+            "use --cookies",
+            "members-only",
+            "available to this channel's members",
+        ]) {
+            DownloadError::LoginRequired(line)
+        } else if has(&["unsupported url"]) {
+            DownloadError::UnsupportedPlatform(line)
+        } else if has(&[
+            "video unavailable",
+            "http error 404",
+            "does not exist",
+            "has been removed",
+            "not found",
+        ]) {
+            DownloadError::VideoNotFound(line)
+        } else if has(&[
+            "unable to download webpage",
+            "connection",
+            "timed out",
+            "network is unreachable",
+            "name or service not known",
+            "temporary failure in name resolution",
+            "http error 5",
+            "ssl",
+        ]) {
+            DownloadError::NetworkError(line)
+        } else if has(&[
+            "unable to extract",
+            "keyerror",
+            "unable to parse",
+            "extractor",
+        ]) {
+            DownloadError::ExtractionError(line)
+        } else {
+            DownloadError::DownloadFailed(line)
+        }
+    }
+
+    /// Whether trying the same download again later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            DownloadError::NetworkError(_) | DownloadError::RateLimited(_)
+        )
+    }
+}
+
 pub type Result<T> = std::result::Result<T, DownloadError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_yt_dlp_stderr() {
+        let cases = [
+            (
+                "WARNING: something\nERROR: [youtube] abc: Video unavailable",
+                "VideoNotFound",
+            ),
+            (
+                "ERROR: [youtube] abc: The uploader has not made this video available in your country",
+                "GeoBlocked",
+            ),
+            (
+                "ERROR: [youtube] abc: Sign in to confirm your age. This video may be inappropriate for some users.",
+                "AgeRestricted",
+            ),
+            ("ERROR: [youtube] abc: Private video. Sign in if you've been granted access", "PrivateVideo"),
+            ("ERROR: [vk] 1_2: This video is only available for registered users. Use --cookies", "LoginRequired"),
+            ("ERROR: Unable to download webpage: HTTP Error 429: Too Many Requests", "RateLimited"),
+            ("ERROR: Postprocessing: ffprobe and ffmpeg not found. Please install", "FfmpegMissing"),
+            ("ERROR: Unable to download webpage: <urlopen error [Errno -2] Name or service not known>", "NetworkError"),
+            ("ERROR: Unsupported URL: https://example.com/", "UnsupportedPlatform"),
+            ("ERROR: [dzen] 1: Unable to extract video data", "ExtractionError"),
+            ("ERROR: something odd", "DownloadFailed"),
+        ];
+
+        for (stderr, expected) in cases {
+            let error = DownloadError::from_yt_dlp_stderr(stderr);
+            assert!(
+                format!("{:?}", error).starts_with(expected),
+                "{} classified as {:?}",
+                stderr,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_keeps_raw_line_and_retryable() {
+        let error = DownloadError::from_yt_dlp_stderr(
+            "[download] 10%\nERROR: Unable to download webpage: HTTP Error 429: Too Many Requests\n",
+        );
+        assert!(error.is_retryable());
+        assert!(error
+            .to_string()
+            .ends_with("ERROR: Unable to download webpage: HTTP Error 429: Too Many Requests"));
+
+        assert!(!DownloadError::from_yt_dlp_stderr("ERROR: Private video").is_retryable());
+        assert!(matches!(
+            DownloadError::from_yt_dlp_stderr(""),
+            DownloadError::DownloadFailed(_)
+        ));
+    }
+}
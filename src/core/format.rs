@@ -0,0 +1,404 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  format.rs                                            TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 09 2025 14:05 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 09 2025 14:05 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VideoCodec {
+    Av1,
+    Vp9,
+    H264,
+}
+
+impl VideoCodec {
+    /// Regex matching the `vcodec` values yt-dlp reports for this codec.
+    fn vcodec_pattern(&self) -> &'static str {
+        match self {
+            VideoCodec::Av1 => "^av01",
+            VideoCodec::Vp9 => "^vp0?9",
+            VideoCodec::H264 => "^(avc|h264)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Container {
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Webm => "webm",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AudioCodec {
+    Mp3,
+    Opus,
+    M4a,
+    Flac,
+}
+
+impl AudioCodec {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Opus => "opus",
+            AudioCodec::M4a => "m4a",
+            AudioCodec::Flac => "flac",
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioCodec::Flac)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Converts the download to an audio file with ffmpeg after fetching the best audio stream.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AudioExtraction {
+    pub codec: AudioCodec,
+    /// Target bitrate in kbit/s; `None` keeps yt-dlp's default VBR quality
+    #[serde(default)]
+    pub bitrate_kbps: Option<u32>,
+    /// Write title, uploader and date tags into the file
+    #[serde(default = "default_true")]
+    pub embed_tags: bool,
+    /// Embed the video thumbnail as cover art
+    #[serde(default = "default_true")]
+    pub embed_cover: bool,
+}
+
+impl AudioExtraction {
+    #[allow(dead_code)]
+    pub fn new(codec: AudioCodec) -> Self {
+        Self {
+            codec,
+            bitrate_kbps: None,
+            embed_tags: true,
+            embed_cover: true,
+        }
+    }
+
+    /// yt-dlp options performing the extraction and tagging.
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-x".to_string(),
+            "--audio-format".to_string(),
+            self.codec.extension().to_string(),
+        ];
+
+        // Bitrate is meaningless for lossless output
+        if let Some(kbps) = self.bitrate_kbps.filter(|_| !self.codec.is_lossless()) {
+            args.push("--audio-quality".to_string());
+            args.push(format!("{}K", kbps));
+        }
+
+        if self.embed_tags {
+            args.push("--embed-metadata".to_string());
+        }
+
+        if self.embed_cover {
+            // Most players cannot show webp cover art
+            args.push("--embed-thumbnail".to_string());
+            args.push("--convert-thumbnails".to_string());
+            args.push("jpg".to_string());
+        }
+
+        args
+    }
+}
+
+/// Quality preferences translated into a yt-dlp `-f` selector.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FormatSpec {
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    #[serde(default)]
+    pub container: Option<Container>,
+    #[serde(default)]
+    pub video_codec: Option<VideoCodec>,
+    #[serde(default)]
+    pub audio_only: bool,
+    /// Exact selector picked from `VideoDownloader::list_formats`; overrides the fields above.
+    #[serde(default)]
+    pub format_id: Option<String>,
+}
+
+impl FormatSpec {
+    /// Returns the `-f` selector, or `None` when yt-dlp's default is wanted.
+    pub fn selector(&self) -> Option<String> {
+        if let Some(ref id) = self.format_id {
+            return Some(id.clone());
+        }
+
+        if self.audio_only {
+            return Some("ba/b".to_string());
+        }
+
+        if self.max_height.is_none() && self.video_codec.is_none() {
+            return None;
+        }
+
+        let height = self
+            .max_height
+            .map(|h| format!("[height<={}]", h))
+            .unwrap_or_default();
+
+        let mut chain = Vec::new();
+        if let Some(codec) = self.video_codec {
+            chain.push(format!(
+                "bv*{}[vcodec~='{}']+ba",
+                height,
+                codec.vcodec_pattern()
+            ));
+        }
+        chain.push(format!("bv*{}+ba", height));
+        chain.push(format!("b{}", height));
+        // Never fail outright because nothing matched the preferences
+        chain.push("b".to_string());
+
+        Some(chain.join("/"))
+    }
+
+    /// Container to merge separate video and audio streams into.
+    pub fn merge_output_format(&self) -> Option<&'static str> {
+        if self.audio_only {
+            return None;
+        }
+        self.container.map(|c| c.extension())
+    }
+}
+
+/// One entry of the `formats` array reported by yt-dlp.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatInfo {
+    pub format_id: String,
+    pub ext: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+    /// Total bitrate in kbit/s
+    pub bitrate: Option<f64>,
+    /// Exact or approximate size in bytes
+    pub filesize: Option<u64>,
+    pub note: Option<String>,
+}
+
+impl FormatInfo {
+    pub fn has_video(&self) -> bool {
+        self.vcodec.as_deref().is_some_and(|c| c != "none")
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.acodec.as_deref().is_some_and(|c| c != "none")
+    }
+
+    /// Selector that downloads this format, adding the best audio to video-only streams.
+    pub fn selector(&self) -> String {
+        if self.has_video() && !self.has_audio() {
+            format!("{}+ba/{}", self.format_id, self.format_id)
+        } else {
+            self.format_id.clone()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawVideoInfo {
+    formats: Option<Vec<RawFormat>>,
+    duration: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawFormat {
+    format_id: Option<String>,
+    ext: Option<String>,
+    width: Option<f64>,
+    height: Option<f64>,
+    fps: Option<f64>,
+    vcodec: Option<String>,
+    acodec: Option<String>,
+    tbr: Option<f64>,
+    filesize: Option<f64>,
+    filesize_approx: Option<f64>,
+    format_note: Option<String>,
+}
+
+/// Parses the `formats` array from yt-dlp's `-J` output.
+///
+/// Storyboards and other formats without audio or video are dropped.
+pub fn parse_formats(json: &str) -> Result<Vec<FormatInfo>> {
+    let raw: RawVideoInfo = serde_json::from_str(json)
+        .map_err(|e| DownloadError::ExtractionError(format!("Invalid format list: {}", e)))?;
+
+    let duration = raw.duration;
+    let mut formats = Vec::new();
+
+    for entry in raw.formats.unwrap_or_default() {
+        let Some(format_id) = entry.format_id else {
+            warn!("Skipping format without id");
+            continue;
+        };
+
+        // Fall back to bitrate * duration when yt-dlp has no size estimate
+        let estimated = match (entry.tbr, duration) {
+            (Some(tbr), Some(secs)) => Some((tbr * 1000.0 / 8.0 * secs) as u64),
+            _ => None,
+        };
+
+        let info = FormatInfo {
+            format_id,
+            ext: entry.ext.unwrap_or_else(|| "unknown".to_string()),
+            width: entry.width.map(|w| w as u32),
+            height: entry.height.map(|h| h as u32),
+            fps: entry.fps,
+            vcodec: entry.vcodec,
+            acodec: entry.acodec,
+            bitrate: entry.tbr,
+            filesize: entry
+                .filesize
+                .or(entry.filesize_approx)
+                .map(|s| s as u64)
+                .or(estimated),
+            note: entry.format_note,
+        };
+
+        if info.has_video() || info.has_audio() {
+            formats.push(info);
+        }
+    }
+
+    info!("Parsed {} formats", formats.len());
+    Ok(formats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_spec_has_no_selector() {
+        let spec = FormatSpec::default();
+        assert_eq!(spec.selector(), None);
+        assert_eq!(spec.merge_output_format(), None);
+    }
+
+    #[test]
+    fn test_selector_with_height_and_codec() {
+        let spec = FormatSpec {
+            max_height: Some(1080),
+            video_codec: Some(VideoCodec::Vp9),
+            container: Some(Container::Mkv),
+            ..Default::default()
+        };
+        assert_eq!(
+            spec.selector().unwrap(),
+            "bv*[height<=1080][vcodec~='^vp0?9']+ba/bv*[height<=1080]+ba/b[height<=1080]/b"
+        );
+        assert_eq!(spec.merge_output_format(), Some("mkv"));
+    }
+
+    #[test]
+    fn test_selector_audio_only_and_explicit() {
+        let spec = FormatSpec {
+            audio_only: true,
+            container: Some(Container::Mp4),
+            ..Default::default()
+        };
+        assert_eq!(spec.selector().unwrap(), "ba/b");
+        assert_eq!(spec.merge_output_format(), None);
+
+        let spec = FormatSpec {
+            format_id: Some("137+ba/137".to_string()),
+            max_height: Some(720),
+            ..Default::default()
+        };
+        assert_eq!(spec.selector().unwrap(), "137+ba/137");
+    }
+
+    #[test]
+    fn test_audio_extraction_args() {
+        let mut audio = AudioExtraction::new(AudioCodec::Mp3);
+        audio.bitrate_kbps = Some(192);
+        assert_eq!(
+            audio.yt_dlp_args(),
+            vec![
+                "-x",
+                "--audio-format",
+                "mp3",
+                "--audio-quality",
+                "192K",
+                "--embed-metadata",
+                "--embed-thumbnail",
+                "--convert-thumbnails",
+                "jpg"
+            ]
+        );
+
+        let flac = AudioExtraction {
+            codec: AudioCodec::Flac,
+            bitrate_kbps: Some(320),
+            embed_tags: false,
+            embed_cover: false,
+        };
+        assert_eq!(flac.yt_dlp_args(), vec!["-x", "--audio-format", "flac"]);
+    }
+
+    #[test]
+    fn test_parse_formats() {
+        let json = r#"{"id":"abc","duration":100,"formats":[
+            {"format_id":"sb0","ext":"mhtml","vcodec":"none","acodec":"none"},
+            {"format_id":"140","ext":"m4a","vcodec":"none","acodec":"mp4a.40.2","tbr":129.5,"filesize":1600000},
+            {"format_id":"137","ext":"mp4","width":1920,"height":1080,"fps":30,"vcodec":"avc1.640028","acodec":"none","tbr":4000,"format_note":"1080p"},
+            {"format_id":"18","ext":"mp4","width":640,"height":360,"vcodec":"avc1.42001E","acodec":"mp4a.40.2","filesize_approx":5000000}
+        ]}"#;
+
+        let formats = parse_formats(json).unwrap();
+        assert_eq!(formats.len(), 3);
+
+        assert!(formats[0].has_audio() && !formats[0].has_video());
+        assert_eq!(formats[0].filesize, Some(1_600_000));
+        assert_eq!(formats[0].selector(), "140");
+
+        assert_eq!(formats[1].height, Some(1080));
+        assert_eq!(formats[1].filesize, Some(50_000_000));
+        assert_eq!(formats[1].selector(), "137+ba/137");
+
+        assert_eq!(formats[2].filesize, Some(5_000_000));
+        assert_eq!(formats[2].selector(), "18");
+    }
+
+    #[test]
+    fn test_parse_formats_invalid_json() {
+        assert!(matches!(
+            parse_formats("not json"),
+            Err(DownloadError::ExtractionError(_))
+        ));
+    }
+}
@@ -11,7 +11,26 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+pub mod archive;
+pub mod auth;
+pub mod bandwidth;
+pub mod cancel;
+pub mod chapters;
+pub mod clip;
+pub mod conflict;
 pub mod downloader;
+pub mod embed;
 pub mod error;
+pub mod format;
+pub mod network;
+pub mod outcome;
+pub mod playlist;
+pub mod progress;
 pub mod queue;
+pub mod retry;
+pub mod scheduler;
 pub mod search;
+pub mod settings;
+pub mod sponsorblock;
+pub mod subtitles;
+pub mod template;
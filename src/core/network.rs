@@ -0,0 +1,522 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  network.rs                                           TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 15 2025 11:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 15 2025 11:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use gio::prelude::*;
+use log::{info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_SOCKET_TIMEOUT: u64 = 30;
+
+/// Proxy schemes understood by both yt-dlp and the HTTP client.
+const PROXY_SCHEMES: [&str; 6] = [
+    "http://",
+    "https://",
+    "socks4://",
+    "socks4a://",
+    "socks5://",
+    "socks5h://",
+];
+
+/// Which IP family connections may use.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IpVersion {
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl IpVersion {
+    pub const ALL: [IpVersion; 3] = [IpVersion::Any, IpVersion::V4, IpVersion::V6];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IpVersion::Any => "Any",
+            IpVersion::V4 => "IPv4 only",
+            IpVersion::V6 => "IPv6 only",
+        }
+    }
+}
+
+/// How every yt-dlp run and HTTP request reaches the network.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Proxy such as "socks5://127.0.0.1:1080"; takes precedence over the system proxy
+    pub proxy: Option<String>,
+    /// Ask GIO for the desktop's proxy when no proxy is set
+    pub use_system_proxy: bool,
+    pub user_agent: Option<String>,
+    /// Extra request headers as name and value
+    pub headers: Vec<(String, String)>,
+    pub ip_version: IpVersion,
+    /// Seconds to wait on a silent connection
+    pub socket_timeout: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            use_system_proxy: false,
+            user_agent: None,
+            headers: Vec::new(),
+            ip_version: IpVersion::Any,
+            socket_timeout: DEFAULT_SOCKET_TIMEOUT,
+        }
+    }
+}
+
+/// Where yt-dlp inputs that are not URLs, such as "ytsearch5:cats", end up.
+const DEFAULT_TARGET: &str = "https://www.youtube.com/";
+
+/// The configuration in effect, and the HTTP client built from it.
+struct Network {
+    settings: Option<NetworkSettings>,
+    client: Option<reqwest::Client>,
+    /// The desktop's proxy per "scheme://host:port"; `None` is a direct connection
+    system_proxies: BTreeMap<String, Option<String>>,
+}
+
+static NETWORK: Mutex<Network> = Mutex::new(Network {
+    settings: None,
+    client: None,
+    system_proxies: BTreeMap::new(),
+});
+
+fn network() -> std::sync::MutexGuard<'static, Network> {
+    NETWORK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Switches all later yt-dlp runs and HTTP requests to `settings`.
+///
+/// Cheap enough for the main thread: the desktop's proxy is looked up later,
+/// per host, away from it.
+pub fn configure(settings: NetworkSettings) {
+    let mut network = network();
+    if network.settings.as_ref() != Some(&settings) {
+        info!(
+            "Network: proxy {}, {:?}, timeout {}s",
+            settings
+                .proxy
+                .as_deref()
+                .unwrap_or(if settings.use_system_proxy {
+                    "system"
+                } else {
+                    "none"
+                }),
+            settings.ip_version,
+            settings.socket_timeout
+        );
+        network.settings = Some(settings);
+        network.client = None;
+        // The desktop settings may have changed along with ours
+        network.system_proxies.clear();
+    }
+}
+
+pub fn current() -> NetworkSettings {
+    network().settings.clone().unwrap_or_default()
+}
+
+/// Network options for a yt-dlp run that fetches `url`.
+///
+/// May block while the desktop's proxy for `url` is looked up, so it belongs on
+/// a blocking thread; async code uses `resolve_yt_dlp_args`.
+pub fn yt_dlp_args(url: &str) -> Vec<String> {
+    let settings = current();
+    let system_proxy = settings
+        .follows_system_proxy()
+        .then(|| lookup_system_proxy(&target_url(url)))
+        .flatten();
+    settings.yt_dlp_args(system_proxy.as_deref())
+}
+
+/// `yt_dlp_args` for async code, looking the proxy up on a blocking thread.
+pub async fn resolve_yt_dlp_args(url: &str) -> Vec<String> {
+    let url = url.to_string();
+    tokio::task::spawn_blocking(move || yt_dlp_args(&url))
+        .await
+        .unwrap_or_else(|_| current().yt_dlp_args(None))
+}
+
+/// The HTTP client shared by searches, thumbnails and API checks, ready for `url`.
+///
+/// The desktop's proxy for `url`'s host is looked up first, on a blocking thread,
+/// so the client never waits for GIO while sending.
+pub async fn http_client_for(url: &str) -> reqwest::Client {
+    if current().follows_system_proxy() {
+        let target = target_url(url);
+        let _ = tokio::task::spawn_blocking(move || lookup_system_proxy(&target)).await;
+    }
+    http_client()
+}
+
+fn http_client() -> reqwest::Client {
+    let mut network = network();
+    if let Some(client) = &network.client {
+        return client.clone();
+    }
+
+    let settings = network.settings.clone().unwrap_or_default();
+    let client = settings.build_client().unwrap_or_else(|e| {
+        warn!("Falling back to a default HTTP client: {}", e);
+        reqwest::Client::new()
+    });
+    network.client = Some(client.clone());
+    client
+}
+
+/// Sends `request`, giving up when no response arrives within the socket timeout.
+pub async fn send(request: reqwest::RequestBuilder) -> std::io::Result<reqwest::Response> {
+    within(current().silence_limit(), request.send()).await
+}
+
+/// Reads the body of `response`, giving up when it stalls for the socket timeout.
+///
+/// A slow transfer is fine as long as data keeps arriving.
+pub async fn read_body(response: reqwest::Response) -> std::io::Result<Vec<u8>> {
+    read_body_within(response, current().silence_limit()).await
+}
+
+async fn read_body_within(
+    mut response: reqwest::Response,
+    limit: Duration,
+) -> std::io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = within(limit, response.chunk()).await? {
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+async fn within<T>(
+    limit: Duration,
+    step: impl Future<Output = reqwest::Result<T>>,
+) -> std::io::Result<T> {
+    match tokio::time::timeout(limit, step).await {
+        Ok(result) => result.map_err(std::io::Error::other),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("no data for {}s", limit.as_secs()),
+        )),
+    }
+}
+
+/// `url` as something a proxy can be looked up for.
+fn target_url(url: &str) -> reqwest::Url {
+    reqwest::Url::parse(url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .unwrap_or_else(|| reqwest::Url::parse(DEFAULT_TARGET).expect("valid default URL"))
+}
+
+/// Proxies differ per host at most, so lookups are shared by scheme, host and port.
+fn proxy_cache_key(url: &reqwest::Url) -> String {
+    format!(
+        "{}://{}:{}",
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+fn cached_system_proxy(url: &reqwest::Url) -> Option<Option<String>> {
+    network().system_proxies.get(&proxy_cache_key(url)).cloned()
+}
+
+/// The desktop's proxy for `url`, asking GIO once per host.
+///
+/// Blocks on a miss, possibly fetching a PAC script, so never call it from the
+/// GTK main thread or a Tokio worker.
+fn lookup_system_proxy(url: &reqwest::Url) -> Option<String> {
+    if let Some(proxy) = cached_system_proxy(url) {
+        return proxy;
+    }
+    let proxy = system_proxy(url.as_str());
+    network()
+        .system_proxies
+        .insert(proxy_cache_key(url), proxy.clone());
+    proxy
+}
+
+impl NetworkSettings {
+    /// Checks the proxy and headers before they are saved.
+    pub fn validate(&self) -> Result<()> {
+        self.build_client().map(|_| ())
+    }
+
+    /// Whether the desktop decides the proxy, per host as its settings or PAC script say.
+    fn follows_system_proxy(&self) -> bool {
+        self.use_system_proxy && self.proxy.is_none()
+    }
+
+    /// yt-dlp options; `system_proxy` is used when following the desktop.
+    pub fn yt_dlp_args(&self, system_proxy: Option<&str>) -> Vec<String> {
+        let mut args = Vec::new();
+
+        if let Some(proxy) = self.proxy_for(system_proxy) {
+            args.extend(["--proxy".to_string(), proxy]);
+        }
+        if let Some(agent) = &self.user_agent {
+            args.extend(["--user-agent".to_string(), agent.clone()]);
+        }
+        for (name, value) in &self.headers {
+            args.extend(["--add-headers".to_string(), format!("{}:{}", name, value)]);
+        }
+        match self.ip_version {
+            IpVersion::Any => {}
+            IpVersion::V4 => args.push("--force-ipv4".to_string()),
+            IpVersion::V6 => args.push("--force-ipv6".to_string()),
+        }
+
+        args.extend([
+            "--socket-timeout".to_string(),
+            self.socket_timeout.to_string(),
+        ]);
+        args
+    }
+
+    fn proxy_for(&self, system_proxy: Option<&str>) -> Option<String> {
+        match &self.proxy {
+            Some(proxy) => Some(proxy.clone()),
+            None if self.use_system_proxy => system_proxy.map(str::to_string),
+            None => None,
+        }
+    }
+
+    /// How long a connection may stay silent, like yt-dlp's `--socket-timeout`.
+    fn silence_limit(&self) -> Duration {
+        Duration::from_secs(self.socket_timeout.max(1))
+    }
+
+    fn build_client(&self) -> Result<reqwest::Client> {
+        let invalid = |e: String| DownloadError::InvalidNetworkSetting(e);
+
+        // Only connecting is bounded here; a whole-request timeout would cut off
+        // slow but steady transfers, so `send` and `read_body` time out on silence
+        let mut builder = reqwest::Client::builder().connect_timeout(self.silence_limit());
+
+        if let Some(proxy) = &self.proxy {
+            // Only a manual proxy needs checking; the desktop's comes from GIO
+            if !PROXY_SCHEMES.iter().any(|s| proxy.starts_with(s)) {
+                return Err(invalid(format!(
+                    "proxy \"{}\" must start with http://, https:// or socks5://",
+                    proxy
+                )));
+            }
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|e| invalid(format!("proxy \"{}\": {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        } else if self.use_system_proxy {
+            builder = builder.proxy(reqwest::Proxy::custom(|url| {
+                match cached_system_proxy(url) {
+                    Some(proxy) => proxy.and_then(|p| reqwest::Url::parse(&p).ok()),
+                    // A host reached without `http_client_for`, e.g. after a redirect:
+                    // go direct this once rather than block the request on GIO
+                    None => {
+                        let url = url.clone();
+                        std::thread::spawn(move || lookup_system_proxy(&url));
+                        None
+                    }
+                }
+            }));
+        }
+
+        if let Some(agent) = &self.user_agent {
+            builder = builder.user_agent(agent.as_str());
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| invalid(format!("\"{}\" is not a header name", name)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| invalid(format!("header {} has an invalid value", name)))?;
+            headers.append(header, value);
+        }
+        builder = builder.default_headers(headers);
+
+        builder = match self.ip_version {
+            IpVersion::Any => builder,
+            IpVersion::V4 => builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            IpVersion::V6 => builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        };
+
+        builder.build().map_err(|e| invalid(e.to_string()))
+    }
+}
+
+/// The desktop's proxy for `url`, or `None` for a direct connection.
+fn system_proxy(url: &str) -> Option<String> {
+    let proxies = match gio::ProxyResolver::default().lookup(url, gio::Cancellable::NONE) {
+        Ok(proxies) => proxies,
+        Err(e) => {
+            warn!("System proxy lookup for {} failed: {}", url, e);
+            return None;
+        }
+    };
+
+    proxies
+        .iter()
+        .map(|p| p.as_str())
+        .find(|p| *p != "direct://")
+        // GIO names SOCKS proxies from the environment plain "socks"
+        .map(|p| p.replacen("socks://", "socks5://", 1))
+}
+
+/// Reads headers written one per line as "Name: Value".
+pub fn parse_headers(text: &str) -> Result<Vec<(String, String)>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(':') {
+            Some((name, value)) if !name.trim().is_empty() => {
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(DownloadError::InvalidNetworkSetting(format!(
+                "\"{}\" is not a \"Name: Value\" header",
+                line
+            ))),
+        })
+        .collect()
+}
+
+pub fn format_headers(headers: &[(String, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yt_dlp_args() {
+        assert_eq!(
+            NetworkSettings::default().yt_dlp_args(Some("http://proxy:3128")),
+            vec!["--socket-timeout", "30"]
+        );
+
+        let settings = NetworkSettings {
+            proxy: Some("socks5://127.0.0.1:1080".to_string()),
+            use_system_proxy: true,
+            user_agent: Some("Mozilla/5.0".to_string()),
+            headers: vec![("Referer".to_string(), "https://vk.com/".to_string())],
+            ip_version: IpVersion::V4,
+            socket_timeout: 10,
+        };
+        assert_eq!(
+            settings.yt_dlp_args(Some("http://proxy:3128")),
+            vec![
+                "--proxy",
+                "socks5://127.0.0.1:1080",
+                "--user-agent",
+                "Mozilla/5.0",
+                "--add-headers",
+                "Referer:https://vk.com/",
+                "--force-ipv4",
+                "--socket-timeout",
+                "10"
+            ]
+        );
+        assert!(settings.validate().is_ok());
+
+        // The resolved desktop proxy is only used when following the system
+        let system = NetworkSettings {
+            use_system_proxy: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            system.yt_dlp_args(Some("http://proxy:3128"))[..2],
+            ["--proxy", "http://proxy:3128"]
+        );
+        assert!(system.build_client().is_ok());
+        assert_eq!(
+            proxy_cache_key(&target_url("ytsearch5:cats")),
+            "https://www.youtube.com:443"
+        );
+        assert_eq!(
+            proxy_cache_key(&target_url("http://vk.com:8080/video")),
+            "http://vk.com:8080"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_body_times_out_on_silence() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            // Promise more than is sent, then go quiet
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let response = reqwest::Client::new()
+            .get(format!("http://{}", address))
+            .send()
+            .await
+            .unwrap();
+        let error = read_body_within(response, Duration::from_millis(200))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers("Referer: https://vk.com/\n\n  X-Token:abc  \n").unwrap();
+        assert_eq!(
+            headers,
+            vec![
+                ("Referer".to_string(), "https://vk.com/".to_string()),
+                ("X-Token".to_string(), "abc".to_string())
+            ]
+        );
+        assert_eq!(
+            format_headers(&headers),
+            "Referer: https://vk.com/\nX-Token: abc"
+        );
+        assert!(parse_headers("no colon").is_err());
+        assert!(parse_headers(": value").is_err());
+
+        let bad_name = NetworkSettings {
+            headers: vec![("Bad Name".to_string(), "x".to_string())],
+            ..Default::default()
+        };
+        assert!(bad_name.validate().is_err());
+        let bad_proxy = NetworkSettings {
+            proxy: Some("ftp://proxy".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_proxy.validate().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  outcome.rs                                           TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 10:05 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 10:05 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::downloader::Platform;
+use crate::core::progress::{format_bytes, format_eta};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Everything known about a finished download.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DownloadOutcome {
+    pub primary_file: PathBuf,
+    /// Subtitles, thumbnails, info.json and any other files yt-dlp produced
+    pub extra_files: Vec<PathBuf>,
+    /// Subtitle side files, also listed in `extra_files`
+    #[serde(default)]
+    pub subtitle_files: Vec<PathBuf>,
+    /// Languages of the saved or embedded subtitles
+    #[serde(default)]
+    pub subtitle_languages: Vec<String>,
+    /// One file per chapter in order, also listed in `extra_files`
+    #[serde(default)]
+    pub chapter_files: Vec<PathBuf>,
+    pub title: String,
+    pub uploader: Option<String>,
+    pub video_id: Option<String>,
+    pub duration: Option<Duration>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub file_size: Option<u64>,
+    pub elapsed: Duration,
+    pub platform: Platform,
+    /// The file already existed and was kept instead of downloading it again
+    #[serde(default)]
+    pub skipped: bool,
+    /// Skipped because the download archive lists the video
+    #[serde(default)]
+    pub archived: bool,
+    /// Optional steps that were left out without failing the download
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl DownloadOutcome {
+    pub fn resolution(&self) -> Option<String> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Some(format!("{}x{}", width, height)),
+            (None, Some(height)) => Some(format!("{}p", height)),
+            _ => None,
+        }
+    }
+
+    /// Codecs joined as "video/audio", leaving out the ones that are missing.
+    pub fn codecs(&self) -> Option<String> {
+        let codecs: Vec<&str> = [&self.video_codec, &self.audio_codec]
+            .into_iter()
+            .filter_map(|c| c.as_deref())
+            .collect();
+
+        if codecs.is_empty() {
+            None
+        } else {
+            Some(codecs.join("/"))
+        }
+    }
+
+    /// One-line description such as "Uploader • 1:02:05 • 1920x1080 • avc1/opus • 1.2 GiB • took 0:42".
+    pub fn details(&self) -> String {
+        let mut parts = Vec::new();
+
+        if self.archived {
+            parts.push("Skipped, already downloaded".to_string());
+        } else if self.skipped {
+            parts.push("Skipped, already exists".to_string());
+        }
+
+        if let Some(ref uploader) = self.uploader {
+            parts.push(uploader.clone());
+        }
+        if let Some(duration) = self.duration {
+            parts.push(format_eta(duration.as_secs()));
+        }
+        parts.extend(self.resolution());
+        parts.extend(self.codecs());
+        if let Some(size) = self.file_size {
+            parts.push(format_bytes(size));
+        }
+        if !self.subtitle_languages.is_empty() {
+            parts.push(format!("subtitles {}", self.subtitle_languages.join(", ")));
+        }
+        if !self.chapter_files.is_empty() {
+            parts.push(format!("{} chapters", self.chapter_files.len()));
+        }
+        parts.push(format!("took {}", format_eta(self.elapsed.as_secs())));
+
+        parts.join(" • ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_details() {
+        let outcome = DownloadOutcome {
+            primary_file: PathBuf::from("/videos/Talk.mkv"),
+            title: "Talk".to_string(),
+            uploader: Some("Conf".to_string()),
+            duration: Some(Duration::from_secs(3725)),
+            width: Some(1920),
+            height: Some(1080),
+            video_codec: Some("vp9".to_string()),
+            audio_codec: Some("opus".to_string()),
+            file_size: Some(1536),
+            subtitle_languages: vec!["en".to_string(), "ru".to_string()],
+            elapsed: Duration::from_secs(42),
+            platform: Platform::YouTube,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            outcome.details(),
+            "Conf • 1:02:05 • 1920x1080 • vp9/opus • 1.5 KiB • subtitles en, ru • took 0:42"
+        );
+    }
+
+    #[test]
+    fn test_audio_only_details() {
+        let outcome = DownloadOutcome {
+            audio_codec: Some("mp3".to_string()),
+            elapsed: Duration::from_secs(5),
+            ..Default::default()
+        };
+
+        assert_eq!(outcome.resolution(), None);
+        assert_eq!(outcome.details(), "mp3 • took 0:05");
+
+        let skipped = DownloadOutcome {
+            skipped: true,
+            ..outcome
+        };
+        assert_eq!(
+            skipped.details(),
+            "Skipped, already exists • mp3 • took 0:05"
+        );
+    }
+}
@@ -0,0 +1,279 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  playlist.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 10 2025 11:02 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 10 2025 11:02 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use crate::core::outcome::DownloadOutcome;
+use crate::core::progress::DownloadProgress;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Which entries of a playlist to download, using 1-based playlist positions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PlaylistSelection {
+    #[default]
+    All,
+    Range {
+        start: u32,
+        end: Option<u32>,
+    },
+    /// Sorted, non-overlapping inclusive ranges; single items are one-entry ranges
+    Items(Vec<(u32, u32)>),
+}
+
+impl PlaylistSelection {
+    /// Parses user input such as `""`, `"3-7"`, `"5-"` or `"1-3,7,9"`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() || spec.eq_ignore_ascii_case("all") {
+            return Ok(PlaylistSelection::All);
+        }
+
+        let invalid = || DownloadError::InvalidPlaylistSelection(spec.to_string());
+        let parse_index = |value: &str| -> Result<u32> {
+            value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or_else(invalid)
+        };
+
+        // Open-ended range such as "5-"
+        if let Some(start) = spec.strip_suffix('-') {
+            if !start.contains([',', '-']) {
+                return Ok(PlaylistSelection::Range {
+                    start: parse_index(start)?,
+                    end: None,
+                });
+            }
+        }
+
+        // Ranges stay ranges, so "1-4000000000" costs no more than "1-4"
+        let mut ranges = Vec::new();
+        for part in spec.split(',') {
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let (start, end) = (parse_index(start)?, parse_index(end)?);
+                    if start > end {
+                        return Err(invalid());
+                    }
+                    ranges.push((start, end));
+                }
+                None => {
+                    let index = parse_index(part)?;
+                    ranges.push((index, index));
+                }
+            }
+        }
+
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        // A single closed range reads better as a range
+        if !spec.contains(',') && spec.contains('-') {
+            if let [(start, end)] = merged[..] {
+                return Ok(PlaylistSelection::Range {
+                    start,
+                    end: Some(end),
+                });
+            }
+        }
+
+        Ok(PlaylistSelection::Items(merged))
+    }
+
+    pub fn includes(&self, index: u32) -> bool {
+        match self {
+            PlaylistSelection::All => true,
+            PlaylistSelection::Range { start, end } => {
+                index >= *start && end.map(|e| index <= e).unwrap_or(true)
+            }
+            PlaylistSelection::Items(ranges) => ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&index)),
+        }
+    }
+}
+
+/// A playlist entry resolved to something yt-dlp can download on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    pub index: u32,
+    /// Video id from the listing, used to look the entry up in the download archive
+    pub id: String,
+    pub title: String,
+    pub url: String,
+}
+
+/// Progress of a playlist download, reported after every progress update of an entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistProgress {
+    /// Position among the selected entries, starting at 1
+    pub position: usize,
+    pub total: usize,
+    pub entry: PlaylistEntry,
+    /// Progress of the current entry
+    pub progress: DownloadProgress,
+    pub overall: f32,
+}
+
+impl PlaylistProgress {
+    pub fn new(
+        position: usize,
+        total: usize,
+        entry: &PlaylistEntry,
+        progress: DownloadProgress,
+    ) -> Self {
+        let finished = position.saturating_sub(1) as f32;
+        let entry_progress = progress.fraction().unwrap_or(0.0);
+        let overall = if total == 0 {
+            1.0
+        } else {
+            ((finished + entry_progress) / total as f32).clamp(0.0, 1.0)
+        };
+
+        Self {
+            position,
+            total,
+            entry: entry.clone(),
+            progress,
+            overall,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntryFailure {
+    pub entry: PlaylistEntry,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaylistDownloadResult {
+    pub title: String,
+    pub folder: PathBuf,
+    pub downloads: Vec<DownloadOutcome>,
+    pub failed: Vec<PlaylistEntryFailure>,
+    /// Optional steps left out for the whole playlist, such as SponsorBlock
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// Turns a playlist title into a single safe directory name.
+pub fn playlist_folder_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "Playlist".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(
+            PlaylistSelection::parse("").unwrap(),
+            PlaylistSelection::All
+        );
+        assert_eq!(
+            PlaylistSelection::parse("3-7").unwrap(),
+            PlaylistSelection::Range {
+                start: 3,
+                end: Some(7)
+            }
+        );
+        assert_eq!(
+            PlaylistSelection::parse("5-").unwrap(),
+            PlaylistSelection::Range {
+                start: 5,
+                end: None
+            }
+        );
+        assert_eq!(
+            PlaylistSelection::parse("9, 1-3,2").unwrap(),
+            PlaylistSelection::Items(vec![(1, 3), (9, 9)])
+        );
+        assert_eq!(
+            PlaylistSelection::parse("1-4000000000,3,4000000001").unwrap(),
+            PlaylistSelection::Items(vec![(1, 4000000001)])
+        );
+        assert_eq!(
+            PlaylistSelection::parse("1-4000000000").unwrap(),
+            PlaylistSelection::Range {
+                start: 1,
+                end: Some(4000000000)
+            }
+        );
+
+        assert!(PlaylistSelection::parse("0").is_err());
+        assert!(PlaylistSelection::parse("7-3").is_err());
+        assert!(PlaylistSelection::parse("abc").is_err());
+    }
+
+    #[test]
+    fn test_selection_includes() {
+        let range = PlaylistSelection::Range {
+            start: 2,
+            end: None,
+        };
+        assert!(!range.includes(1));
+        assert!(range.includes(200));
+
+        let items = PlaylistSelection::Items(vec![(1, 1), (4, 6)]);
+        assert!(items.includes(5));
+        assert!(!items.includes(2));
+    }
+
+    #[test]
+    fn test_playlist_progress_overall() {
+        let entry = PlaylistEntry {
+            index: 3,
+            id: "c3".to_string(),
+            title: "Third".to_string(),
+            url: "https://example.com/3".to_string(),
+        };
+        let half = DownloadProgress {
+            downloaded_bytes: Some(50),
+            total_bytes: Some(100),
+            ..Default::default()
+        };
+        let progress = PlaylistProgress::new(2, 4, &entry, half);
+        assert!((progress.overall - 0.375).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_playlist_folder_name() {
+        assert_eq!(playlist_folder_name("My: Mix / 2025"), "My_ Mix _ 2025");
+        assert_eq!(playlist_folder_name(" .. "), "Playlist");
+        assert_eq!(playlist_folder_name("Lectures"), "Lectures");
+    }
+}
@@ -0,0 +1,287 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  progress.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 10 2025 16:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 10 2025 16:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use serde::Deserialize;
+
+/// Prefix of the lines produced by our `--progress-template` for downloads.
+pub const PROGRESS_MARKER: &str = "VDOWNLOADER_PROGRESS:";
+/// Prefix of the lines produced by our `--progress-template` for post-processors.
+pub const POSTPROCESS_MARKER: &str = "VDOWNLOADER_POSTPROCESS:";
+
+/// What yt-dlp is busy with at the moment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownloadStage {
+    #[default]
+    Extracting,
+    DownloadingVideo,
+    DownloadingAudio,
+    /// A single stream carrying both video and audio, or one we cannot tell apart
+    Downloading,
+    Merging,
+    PostProcessing,
+}
+
+impl DownloadStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DownloadStage::Extracting => "Extracting",
+            DownloadStage::DownloadingVideo => "Downloading video",
+            DownloadStage::DownloadingAudio => "Downloading audio",
+            DownloadStage::Downloading => "Downloading",
+            DownloadStage::Merging => "Merging",
+            DownloadStage::PostProcessing => "Post-processing",
+        }
+    }
+}
+
+/// A progress update of a single download.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    pub stage: DownloadStage,
+    pub downloaded_bytes: Option<u64>,
+    /// Exact size when known, otherwise yt-dlp's estimate
+    pub total_bytes: Option<u64>,
+    /// Bytes per second
+    pub speed: Option<f64>,
+    /// Seconds remaining
+    pub eta: Option<u64>,
+    pub fragment_index: Option<u32>,
+    pub fragment_count: Option<u32>,
+    /// Format id of the stream being fetched
+    pub stream: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawProgress {
+    status: Option<String>,
+    downloaded_bytes: Option<f64>,
+    total_bytes: Option<f64>,
+    total_bytes_estimate: Option<f64>,
+    speed: Option<f64>,
+    eta: Option<f64>,
+    fragment_index: Option<u32>,
+    fragment_count: Option<u32>,
+    postprocessor: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawStream {
+    format_id: Option<String>,
+    vcodec: Option<String>,
+    acodec: Option<String>,
+}
+
+impl DownloadProgress {
+    pub fn stage(stage: DownloadStage) -> Self {
+        Self {
+            stage,
+            ..Default::default()
+        }
+    }
+
+    /// Arguments that make yt-dlp print progress lines understood by [`DownloadProgress::parse_line`].
+    ///
+    /// Each line carries the progress dict and the stream's codecs as two JSON
+    /// documents separated by a tab, which JSON strings never contain unescaped.
+    pub fn yt_dlp_args() -> Vec<String> {
+        vec![
+            "--progress".to_string(),
+            "--newline".to_string(),
+            "--progress-template".to_string(),
+            format!(
+                "download:{}%(progress)j\t%(info.{{format_id,vcodec,acodec}})j",
+                PROGRESS_MARKER
+            ),
+            "--progress-template".to_string(),
+            format!("postprocess:{}%(progress)j", POSTPROCESS_MARKER),
+        ]
+    }
+
+    /// Parses one line of yt-dlp output, returning `None` for anything that is not a progress line.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        if let Some(rest) = line.strip_prefix(PROGRESS_MARKER) {
+            let (progress, stream) = rest.split_once('\t').unwrap_or((rest, ""));
+            let raw: RawProgress = serde_json::from_str(progress.trim()).ok()?;
+            let stream: RawStream = serde_json::from_str(stream.trim()).unwrap_or_default();
+            return Some(Self::from_download(raw, stream));
+        }
+
+        if let Some(rest) = line.strip_prefix(POSTPROCESS_MARKER) {
+            let raw: RawProgress = serde_json::from_str(rest.trim()).ok()?;
+            let stage = match raw.postprocessor.as_deref() {
+                Some("Merger") => DownloadStage::Merging,
+                _ => DownloadStage::PostProcessing,
+            };
+            return Some(Self::stage(stage));
+        }
+
+        // Older yt-dlp builds do not run the template for the merger
+        if line.starts_with("[Merger]") {
+            return Some(Self::stage(DownloadStage::Merging));
+        }
+
+        None
+    }
+
+    fn from_download(raw: RawProgress, stream: RawStream) -> Self {
+        let has = |codec: &Option<String>| codec.as_deref().is_some_and(|c| c != "none");
+        let stage = match (has(&stream.vcodec), has(&stream.acodec)) {
+            (true, false) => DownloadStage::DownloadingVideo,
+            (false, true) => DownloadStage::DownloadingAudio,
+            _ => DownloadStage::Downloading,
+        };
+
+        let mut progress = Self {
+            stage,
+            downloaded_bytes: raw.downloaded_bytes.map(|b| b as u64),
+            total_bytes: raw
+                .total_bytes
+                .or(raw.total_bytes_estimate)
+                .map(|b| b as u64),
+            speed: raw.speed,
+            eta: raw.eta.map(|e| e as u64),
+            fragment_index: raw.fragment_index,
+            fragment_count: raw.fragment_count,
+            stream: stream.format_id,
+        };
+
+        // The final "finished" report may omit the total
+        if raw.status.as_deref() == Some("finished") && progress.total_bytes.is_none() {
+            progress.total_bytes = progress.downloaded_bytes;
+        }
+
+        progress
+    }
+
+    /// Completed share of the current stream between 0.0 and 1.0, if it can be told.
+    pub fn fraction(&self) -> Option<f32> {
+        match (self.downloaded_bytes, self.total_bytes) {
+            (Some(done), Some(total)) if total > 0 => {
+                Some((done as f64 / total as f64).clamp(0.0, 1.0) as f32)
+            }
+            _ => match (self.fragment_index, self.fragment_count) {
+                (Some(index), Some(count)) if count > 0 => {
+                    Some((index as f32 / count as f32).clamp(0.0, 1.0))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Short human readable summary, e.g. "Downloading video • 45% • 2.0 MiB/s • ETA 0:05".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self.stage.label().to_string()];
+
+        if matches!(
+            self.stage,
+            DownloadStage::Downloading
+                | DownloadStage::DownloadingVideo
+                | DownloadStage::DownloadingAudio
+        ) {
+            if let Some(fraction) = self.fraction() {
+                parts.push(format!("{:.0}%", fraction * 100.0));
+            }
+            if let Some(speed) = self.speed {
+                parts.push(format!("{}/s", format_bytes(speed as u64)));
+            }
+            if let Some(eta) = self.eta {
+                parts.push(format!("ETA {}", format_eta(eta)));
+            }
+        }
+
+        parts.join(" • ")
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+pub fn format_eta(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_download_line() {
+        let line = format!(
+            "{}{{\"status\": \"downloading\", \"downloaded_bytes\": 1048576, \"total_bytes\": null, \"total_bytes_estimate\": 4194304.0, \"speed\": 2097152.5, \"eta\": 2, \"fragment_index\": 3, \"fragment_count\": 12}}\t{{\"format_id\": \"137\", \"vcodec\": \"avc1.640028\", \"acodec\": \"none\"}}",
+            PROGRESS_MARKER
+        );
+        let progress = DownloadProgress::parse_line(&line).unwrap();
+
+        assert_eq!(progress.stage, DownloadStage::DownloadingVideo);
+        assert_eq!(progress.downloaded_bytes, Some(1048576));
+        assert_eq!(progress.total_bytes, Some(4194304));
+        assert_eq!(progress.eta, Some(2));
+        assert_eq!(progress.fragment_index, Some(3));
+        assert_eq!(progress.stream.as_deref(), Some("137"));
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(
+            progress.summary(),
+            "Downloading video • 25% • 2.0 MiB/s • ETA 0:02"
+        );
+    }
+
+    #[test]
+    fn test_parse_postprocess_and_other_lines() {
+        let merging = format!(
+            "{}{{\"status\": \"started\", \"postprocessor\": \"Merger\"}}",
+            POSTPROCESS_MARKER
+        );
+        assert_eq!(
+            DownloadProgress::parse_line(&merging).unwrap().stage,
+            DownloadStage::Merging
+        );
+
+        let audio = format!(
+            "{}{{\"status\": \"started\", \"postprocessor\": \"ExtractAudio\"}}",
+            POSTPROCESS_MARKER
+        );
+        assert_eq!(
+            DownloadProgress::parse_line(&audio).unwrap().stage,
+            DownloadStage::PostProcessing
+        );
+
+        assert!(DownloadProgress::parse_line("[youtube] abc: Downloading webpage").is_none());
+        assert!(DownloadProgress::parse_line(&format!("{}not json", PROGRESS_MARKER)).is_none());
+    }
+
+    #[test]
+    fn test_format_helpers() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_eta(65), "1:05");
+        assert_eq!(format_eta(3725), "1:02:05");
+    }
+}
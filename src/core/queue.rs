@@ -12,76 +12,454 @@
 /*****************************************************************************/
 
 use crate::core::downloader::{DownloadRequest, DownloadStatus};
+use crate::core::settings::{data_dir, write_atomically};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::sync::{RwLock, RwLockWriteGuard};
+
+const QUEUE_FILE: &str = "queue.json";
+
+/// Pending items of a higher priority start before all lower ones.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::Low, Priority::Normal, Priority::High];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// A change to where an item sits in the queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueueMove {
+    /// One place up among items of the same priority
+    Up,
+    Down,
+    /// First among items of the same priority
+    Top,
+    /// First of all, by raising the priority to high
+    Next,
+    /// Just before another item, taking over its priority
+    Before(String),
+}
+
+/// Why one attempt at an item failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedAttempt {
+    pub at: SystemTime,
+    pub error: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct QueueItem {
     pub id: String,
     pub request: DownloadRequest,
     pub status: DownloadStatus,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Every failed attempt, oldest first; only a log
+    #[serde(default)]
+    pub history: Vec<FailedAttempt>,
+    /// Failed attempts since the item was last queued, resumed or restored,
+    /// which is what the retry budget counts
+    #[serde(default)]
+    pub failed_attempts: u32,
 }
 
-#[allow(dead_code)]
+impl QueueItem {
+    /// Whether the item may start now.
+    fn is_ready(&self, now: SystemTime) -> bool {
+        match &self.status {
+            DownloadStatus::Pending => true,
+            DownloadStatus::Retrying { next_attempt, .. } => *next_attempt <= now,
+            _ => false,
+        }
+    }
+}
+
+/// The queue's contents, which is also what its journal holds.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct QueueState {
+    next_id: usize,
+    items: HashMap<String, QueueItem>,
+    /// Item ids in queue order; priorities are applied on top of it
+    order: Vec<String>,
+}
+
+impl QueueState {
+    /// Items in the order they run: by priority, then by queue position.
+    fn ordered(&self) -> Vec<&QueueItem> {
+        let mut items: Vec<&QueueItem> = self
+            .order
+            .iter()
+            .filter_map(|id| self.items.get(id))
+            .collect();
+        // The sort is stable, so queue order holds within a priority
+        items.sort_by_key(|item| std::cmp::Reverse(item.priority));
+        items
+    }
+
+    /// Makes `order` list every item exactly once, e.g. for journals written before it existed.
+    fn repair_order(&mut self) {
+        let items = &self.items;
+        let mut order: Vec<String> = Vec::with_capacity(items.len());
+        for id in &self.order {
+            if items.contains_key(id) && !order.contains(id) {
+                order.push(id.clone());
+            }
+        }
+
+        let mut missing: Vec<&String> = items.keys().filter(|id| !order.contains(id)).collect();
+        missing.sort_by_key(|id| sequence_number(id));
+        order.extend(missing.into_iter().cloned());
+        self.order = order;
+    }
+
+    fn apply_move(&mut self, id: &str, movement: QueueMove) -> bool {
+        let Some(priority) = self.items.get(id).map(|item| item.priority) else {
+            return false;
+        };
+        let Some(position) = self.order.iter().position(|o| o == id) else {
+            return false;
+        };
+        let same_priority = |other: &String| {
+            self.items
+                .get(other)
+                .is_some_and(|item| item.priority == priority)
+        };
+
+        let target = match &movement {
+            QueueMove::Up => {
+                let Some(above) = self.order[..position].iter().rposition(same_priority) else {
+                    return false;
+                };
+                above
+            }
+            QueueMove::Down => {
+                let Some(below) = self.order[position + 1..].iter().position(same_priority) else {
+                    return false;
+                };
+                // Counted from after the item, which is removed first
+                position + 1 + below
+            }
+            QueueMove::Top | QueueMove::Next => 0,
+            QueueMove::Before(other) => {
+                if other == id {
+                    return false;
+                }
+                let Some(other_priority) = self.items.get(other).map(|item| item.priority) else {
+                    return false;
+                };
+                if let Some(item) = self.items.get_mut(id) {
+                    item.priority = other_priority;
+                }
+                self.order.remove(position);
+                let index = self.order.iter().position(|o| o == other).unwrap_or(0);
+                self.order.insert(index, id.to_string());
+                return true;
+            }
+        };
+
+        if movement == QueueMove::Next {
+            if let Some(item) = self.items.get_mut(id) {
+                item.priority = Priority::High;
+            }
+        }
+
+        let moved = self.order.remove(position);
+        self.order.insert(target, moved);
+        true
+    }
+}
+
+/// Where the queue is journaled, and which snapshot of it is on disk.
+struct Journal {
+    path: PathBuf,
+    /// Numbers snapshots in the order they were taken, under the state lock
+    taken: AtomicU64,
+    /// Newest snapshot written, so a late write never replaces a newer one
+    written: Mutex<u64>,
+}
+
+impl Journal {
+    fn write(&self, snapshot: u64, contents: &[u8]) {
+        let mut written = self.written.lock().unwrap_or_else(|e| e.into_inner());
+        if *written > snapshot {
+            return;
+        }
+        match write_atomically(&self.path, contents) {
+            Ok(()) => *written = snapshot,
+            Err(e) => warn!("Failed to journal the download queue: {}", e),
+        }
+    }
+}
+
+/// Downloads waiting, running and finished; clones share the same items.
+#[derive(Clone)]
 pub struct DownloadQueue {
-    items: Arc<RwLock<HashMap<String, QueueItem>>>,
-    next_id: Arc<RwLock<usize>>,
+    state: Arc<RwLock<QueueState>>,
+    /// Journal rewritten after every change; `None` keeps the queue in memory only
+    journal: Option<Arc<Journal>>,
 }
 
 #[allow(dead_code)]
 impl DownloadQueue {
     pub fn new() -> Self {
         Self {
-            items: Arc::new(RwLock::new(HashMap::new())),
-            next_id: Arc::new(RwLock::new(0)),
+            state: Arc::new(RwLock::new(QueueState::default())),
+            journal: None,
+        }
+    }
+
+    /// Restores the queue journaled in the data directory.
+    pub fn load() -> Self {
+        Self::open(data_dir().join(QUEUE_FILE))
+    }
+
+    /// Restores the queue from `path` and keeps journaling to it.
+    ///
+    /// Items that were downloading when the app stopped go back to pending
+    /// with a fresh retry budget, so they run again and yt-dlp resumes their
+    /// partial files. Items waiting to retry keep their next attempt time and
+    /// paused items stay paused.
+    pub fn open(path: PathBuf) -> Self {
+        let mut state = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable queue {}: {}", path.display(), e);
+                QueueState::default()
+            }),
+            Err(_) => QueueState::default(),
+        };
+        state.repair_order();
+
+        let mut interrupted = 0;
+        for item in state.items.values_mut() {
+            if matches!(item.status, DownloadStatus::Downloading { .. }) {
+                item.status = DownloadStatus::Pending;
+                item.request.resume = true;
+                item.failed_attempts = 0;
+                interrupted += 1;
+            }
+        }
+        if !state.items.is_empty() {
+            info!(
+                "Restored {} queued download(s), {} to resume",
+                state.items.len(),
+                interrupted
+            );
+        }
+
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            journal: Some(Arc::new(Journal {
+                path,
+                taken: AtomicU64::new(0),
+                written: Mutex::new(0),
+            })),
+        }
+    }
+
+    /// Journals the changed state, releasing the lock before touching the disk.
+    async fn persist(&self, state: RwLockWriteGuard<'_, QueueState>) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let contents = match serde_json::to_vec_pretty(&*state) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Failed to encode the download queue: {}", e);
+                return;
+            }
+        };
+        let snapshot = journal.taken.fetch_add(1, Ordering::SeqCst) + 1;
+        drop(state);
+
+        let journal = journal.clone();
+        let written = tokio::task::spawn_blocking(move || journal.write(snapshot, &contents)).await;
+        if let Err(e) = written {
+            warn!("Failed to journal the download queue: {}", e);
         }
     }
 
     pub async fn add(&self, request: DownloadRequest) -> String {
-        let mut id_counter = self.next_id.write().await;
-        let id = format!("download_{}", *id_counter);
-        *id_counter += 1;
+        let mut state = self.state.write().await;
+        let id = format!("download_{}", state.next_id);
+        state.next_id += 1;
 
         let item = QueueItem {
             id: id.clone(),
             request,
             status: DownloadStatus::Pending,
+            priority: Priority::Normal,
+            history: Vec::new(),
+            failed_attempts: 0,
         };
 
-        let mut items = self.items.write().await;
-        items.insert(id.clone(), item);
+        state.items.insert(id.clone(), item);
+        state.order.push(id.clone());
+        self.persist(state).await;
 
         id
     }
 
     pub async fn get(&self, id: &str) -> Option<QueueItem> {
-        let items = self.items.read().await;
-        items.get(id).cloned()
+        let state = self.state.read().await;
+        state.items.get(id).cloned()
     }
 
     pub async fn update_status(&self, id: &str, status: DownloadStatus) {
-        let mut items = self.items.write().await;
-        if let Some(item) = items.get_mut(id) {
+        let mut state = self.state.write().await;
+        if let Some(item) = state.items.get_mut(id) {
+            // Progress alone is not worth a write; a restart resumes from the file anyway
+            let progress_only = matches!(
+                (&item.status, &status),
+                (
+                    DownloadStatus::Downloading { .. },
+                    DownloadStatus::Downloading { .. }
+                )
+            );
             item.status = status;
+            if !progress_only {
+                self.persist(state).await;
+            }
+        }
+    }
+
+    /// Marks a ready item as downloading; false if it can no longer start.
+    pub async fn start(&self, id: &str) -> bool {
+        let mut state = self.state.write().await;
+        match state.items.get_mut(id) {
+            Some(item) if item.is_ready(SystemTime::now()) => {
+                item.status = DownloadStatus::Downloading { progress: 0.0 };
+                self.persist(state).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Logs a failed attempt; returns how many failed since the item was last
+    /// queued, resumed or restored.
+    pub async fn record_failure(&self, id: &str, error: String) -> u32 {
+        let mut state = self.state.write().await;
+        let Some(item) = state.items.get_mut(id) else {
+            return 0;
+        };
+        item.history.push(FailedAttempt {
+            at: SystemTime::now(),
+            error,
+        });
+        item.failed_attempts += 1;
+        let failures = item.failed_attempts;
+        self.persist(state).await;
+        failures
+    }
+
+    /// Pauses a waiting or downloading item, returning its new status.
+    ///
+    /// A running item must have stopped first; this only records the state.
+    pub async fn pause(&self, id: &str) -> Option<DownloadStatus> {
+        let mut state = self.state.write().await;
+        let item = state.items.get_mut(id)?;
+        let progress = match item.status {
+            DownloadStatus::Pending | DownloadStatus::Retrying { .. } => 0.0,
+            DownloadStatus::Downloading { progress } => progress,
+            _ => return None,
+        };
+        item.status = DownloadStatus::Paused { progress };
+        let status = item.status.clone();
+        self.persist(state).await;
+        Some(status)
+    }
+
+    /// Puts a paused item back in line, continuing its partial files with a
+    /// fresh retry budget.
+    pub async fn resume(&self, id: &str) -> bool {
+        let mut state = self.state.write().await;
+        match state.items.get_mut(id) {
+            Some(item) if matches!(item.status, DownloadStatus::Paused { .. }) => {
+                item.status = DownloadStatus::Pending;
+                item.request.resume = true;
+                item.failed_attempts = 0;
+                self.persist(state).await;
+                true
+            }
+            _ => false,
         }
     }
 
     pub async fn remove(&self, id: &str) {
-        let mut items = self.items.write().await;
-        items.remove(id);
+        let mut state = self.state.write().await;
+        if state.items.remove(id).is_some() {
+            state.order.retain(|o| o != id);
+            self.persist(state).await;
+        }
     }
 
+    /// Moves an item within the queue; returns whether anything changed.
+    pub async fn move_item(&self, id: &str, movement: QueueMove) -> bool {
+        let mut state = self.state.write().await;
+        let moved = state.apply_move(id, movement);
+        if moved {
+            self.persist(state).await;
+        }
+        moved
+    }
+
+    /// Returns whether the priority changed.
+    pub async fn set_priority(&self, id: &str, priority: Priority) -> bool {
+        let mut state = self.state.write().await;
+        match state.items.get_mut(id) {
+            Some(item) if item.priority != priority => {
+                item.priority = priority;
+                self.persist(state).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Up to `count` items that may start now, in the order they should start.
+    pub async fn next_pending(&self, count: usize) -> Vec<QueueItem> {
+        let now = SystemTime::now();
+        self.list_all()
+            .await
+            .into_iter()
+            .filter(|item| item.is_ready(now))
+            .take(count)
+            .collect()
+    }
+
+    /// All items in queue order.
     pub async fn list_all(&self) -> Vec<QueueItem> {
-        let items = self.items.read().await;
-        items.values().cloned().collect()
+        let state = self.state.read().await;
+        state.ordered().into_iter().cloned().collect()
     }
 
     pub async fn clear(&self) {
-        let mut items = self.items.write().await;
-        items.clear();
+        let mut state = self.state.write().await;
+        state.items.clear();
+        state.order.clear();
+        self.persist(state).await;
     }
 }
 
@@ -91,10 +469,20 @@ impl Default for DownloadQueue {
     }
 }
 
+/// The counter in a "download_{n}" id, which tells when an item was added.
+fn sequence_number(id: &str) -> usize {
+    id.rsplit('_')
+        .next()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(usize::MAX)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::conflict::ConflictPolicy;
     use crate::core::downloader::Platform;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_queue_operations() {
@@ -104,7 +492,8 @@ mod tests {
             url: "https://www.youtube.com/watch?v=test".to_string(),
             platform: Platform::YouTube,
             output_path: None,
-            overwrite: false,
+            conflict: ConflictPolicy::Skip,
+            ..Default::default()
         };
 
         let id = queue.add(request.clone()).await;
@@ -123,4 +512,200 @@ mod tests {
         let item = queue.get(&id).await;
         assert!(item.is_none());
     }
+
+    #[tokio::test]
+    async fn test_next_pending_in_order() {
+        let queue = DownloadQueue::new();
+        let mut ids = Vec::new();
+        for _ in 0..12 {
+            ids.push(queue.add(DownloadRequest::default()).await);
+        }
+        queue
+            .update_status(&ids[0], DownloadStatus::Downloading { progress: 0.5 })
+            .await;
+
+        let pending: Vec<String> = queue
+            .next_pending(10)
+            .await
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(pending, ids[1..11].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_journal_restores_interrupted_items() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_queue_{}", std::process::id()));
+        let path = dir.join(QUEUE_FILE);
+
+        let queue = DownloadQueue::open(path.clone());
+        let done = queue.add(DownloadRequest::default()).await;
+        let running = queue.add(DownloadRequest::default()).await;
+        let dropped = queue.add(DownloadRequest::default()).await;
+        let retrying = queue.add(DownloadRequest::default()).await;
+        let next_attempt = SystemTime::now() + Duration::from_secs(60);
+        assert_eq!(
+            queue
+                .record_failure(&retrying, "timed out".to_string())
+                .await,
+            1
+        );
+        queue
+            .update_status(
+                &retrying,
+                DownloadStatus::Retrying {
+                    attempt: 1,
+                    max_attempts: 3,
+                    next_attempt,
+                    error: "timed out".to_string(),
+                },
+            )
+            .await;
+        queue
+            .update_status(
+                &done,
+                DownloadStatus::Failed {
+                    error: "gone".to_string(),
+                },
+            )
+            .await;
+        queue
+            .update_status(&running, DownloadStatus::Downloading { progress: 0.4 })
+            .await;
+        queue.remove(&dropped).await;
+
+        let restored = DownloadQueue::open(path.clone());
+        let items = restored.list_all().await;
+        assert_eq!(items.len(), 3);
+        assert!(matches!(items[0].status, DownloadStatus::Failed { .. }));
+        assert_eq!(items[1].id, running);
+        assert!(matches!(items[1].status, DownloadStatus::Pending));
+        assert!(matches!(
+            items[2].status,
+            DownloadStatus::Retrying { next_attempt: at, .. } if at == next_attempt
+        ));
+        assert_eq!(items[2].failed_attempts, 1);
+
+        // Ids keep counting where the previous session stopped
+        let next = restored.add(DownloadRequest::default()).await;
+        assert_eq!(next, "download_4");
+        assert!(!dir.join("queue.json.tmp").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_priorities_and_moves() {
+        let queue = DownloadQueue::new();
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(queue.add(DownloadRequest::default()).await);
+        }
+        let order = |items: Vec<QueueItem>| -> Vec<String> {
+            items.into_iter().map(|item| item.id).collect()
+        };
+        let (a, b, c, d) = (ids[0].as_str(), &*ids[1], &*ids[2], &*ids[3]);
+
+        assert!(queue.set_priority(c, Priority::High).await);
+        assert!(queue.set_priority(a, Priority::Low).await);
+        assert!(!queue.set_priority(a, Priority::Low).await);
+        assert_eq!(order(queue.list_all().await), vec![c, b, d, a]);
+
+        // Moves stay within the item's priority
+        assert!(queue.move_item(d, QueueMove::Up).await);
+        assert_eq!(order(queue.list_all().await), vec![c, d, b, a]);
+        assert!(!queue.move_item(c, QueueMove::Up).await);
+        assert!(queue.move_item(d, QueueMove::Down).await);
+        assert!(!queue.move_item(a, QueueMove::Down).await);
+        assert!(queue.move_item(d, QueueMove::Top).await);
+        assert_eq!(order(queue.list_all().await), vec![c, d, b, a]);
+
+        assert!(queue.move_item(a, QueueMove::Next).await);
+        assert_eq!(order(queue.list_all().await), vec![a, c, d, b]);
+
+        // Dropping takes over the priority of the item dropped on
+        assert!(queue.move_item(c, QueueMove::Before(b.to_string())).await);
+        assert_eq!(queue.get(c).await.unwrap().priority, Priority::Normal);
+        assert_eq!(order(queue.list_all().await), vec![a, d, c, b]);
+
+        assert_eq!(order(queue.next_pending(2).await), vec![a, d]);
+    }
+
+    #[tokio::test]
+    async fn test_pause_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_pause_{}", std::process::id()));
+        let path = dir.join(QUEUE_FILE);
+
+        let queue = DownloadQueue::open(path.clone());
+        let paused = queue.add(DownloadRequest::default()).await;
+        let waiting = queue.add(DownloadRequest::default()).await;
+        assert!(queue.start(&paused).await);
+        queue.record_failure(&paused, "timed out".to_string()).await;
+        assert_eq!(
+            queue.record_failure(&paused, "timed out".to_string()).await,
+            2
+        );
+        queue
+            .update_status(&paused, DownloadStatus::Downloading { progress: 0.4 })
+            .await;
+        assert!(matches!(
+            queue.pause(&paused).await,
+            Some(DownloadStatus::Paused { progress }) if progress == 0.4
+        ));
+        assert!(queue.pause(&paused).await.is_none());
+
+        let restored = DownloadQueue::open(path);
+        let pending: Vec<String> = restored
+            .next_pending(10)
+            .await
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(pending, vec![waiting.clone()]);
+
+        assert!(restored.resume(&paused).await);
+        assert!(!restored.resume(&waiting).await);
+        let item = restored.get(&paused).await.unwrap();
+        assert!(matches!(item.status, DownloadStatus::Pending));
+        assert!(item.request.resume);
+        // Resuming starts a new retry budget; the log keeps every failure
+        assert_eq!(
+            restored
+                .record_failure(&paused, "timed out".to_string())
+                .await,
+            1
+        );
+        assert_eq!(restored.get(&paused).await.unwrap().history.len(), 3);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_retrying_items_wait_for_their_time() {
+        let queue = DownloadQueue::new();
+        let later = queue.add(DownloadRequest::default()).await;
+        let due = queue.add(DownloadRequest::default()).await;
+        for (id, delay) in [(&later, 60), (&due, 0)] {
+            let failures = queue.record_failure(id, "timed out".to_string()).await;
+            assert_eq!(failures, 1);
+            let status = DownloadStatus::Retrying {
+                attempt: failures,
+                max_attempts: 3,
+                next_attempt: SystemTime::now() + Duration::from_secs(delay),
+                error: "timed out".to_string(),
+            };
+            queue.update_status(id, status).await;
+        }
+
+        let ready: Vec<String> = queue
+            .next_pending(10)
+            .await
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(ready, vec![due.clone()]);
+        assert!(!queue.start(&later).await);
+        assert!(queue.start(&due).await);
+        assert_eq!(queue.get(&due).await.unwrap().history.len(), 1);
+    }
 }
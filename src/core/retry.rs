@@ -0,0 +1,75 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  retry.rs                                             TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 16 2025 14:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 16 2025 14:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::DownloadError;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Attempts per queued download, the first one included.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+
+/// Wait after the first failed attempt; doubled after each further one.
+const BASE_DELAY: Duration = Duration::from_secs(10);
+
+const MAX_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// Whether another attempt is due after `failures` failed ones ending in `error`.
+///
+/// Only errors that may clear up by themselves, like network drops and rate
+/// limits, are worth retrying.
+pub fn should_retry(error: &DownloadError, failures: u32, max_attempts: u32) -> bool {
+    error.is_retryable() && failures < max_attempts
+}
+
+/// How long to wait before the attempt following `failures` failed ones.
+///
+/// Up to half of the delay is randomly taken off, so downloads that failed
+/// together do not all hit the site again at the same moment.
+pub fn backoff_delay(failures: u32) -> Duration {
+    let doublings = failures.saturating_sub(1).min(16);
+    let delay = BASE_DELAY.saturating_mul(1 << doublings).min(MAX_DELAY);
+    delay.mul_f64(1.0 - random_fraction() / 2.0)
+}
+
+/// A random number in `[0, 1]`; every `RandomState` is seeded differently.
+fn random_fraction() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_within_bounds() {
+        for failures in 1..=12 {
+            let full = BASE_DELAY
+                .saturating_mul(1 << (failures - 1))
+                .min(MAX_DELAY);
+            let delay = backoff_delay(failures);
+            assert!(delay <= full);
+            assert!(delay >= full / 2);
+        }
+        assert!(backoff_delay(40) <= MAX_DELAY);
+
+        let rate_limited = DownloadError::RateLimited("HTTP Error 429".to_string());
+        assert!(should_retry(&rate_limited, 1, 3));
+        assert!(!should_retry(&rate_limited, 3, 3));
+        assert!(!should_retry(
+            &DownloadError::VideoNotFound(String::new()),
+            1,
+            3
+        ));
+    }
+}
@@ -0,0 +1,505 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  scheduler.rs                                         TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 16 2025 10:15 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 16 2025 10:15 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::cancel::CancellationToken;
+use crate::core::downloader::{DownloadRequest, DownloadStatus, VideoDownloader};
+use crate::core::error::{DownloadError, Result};
+use crate::core::queue::{DownloadQueue, Priority, QueueItem, QueueMove};
+use crate::core::retry::{self, DEFAULT_MAX_ATTEMPTS};
+use crate::core::settings::default_download_dir;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+
+pub const DEFAULT_PARALLEL_DOWNLOADS: usize = 2;
+
+/// Changes the scheduler reports to the UI.
+#[derive(Debug, Clone)]
+pub enum QueueEvent {
+    /// An item joined the queue, or was restored from the previous session
+    Added { item: Box<QueueItem> },
+    /// A running item made progress; `fraction` is `None` while it cannot be measured
+    Progress {
+        id: String,
+        fraction: Option<f32>,
+        text: String,
+    },
+    /// An item started or finished
+    Status { id: String, status: DownloadStatus },
+    /// An item failed for good; its status holds the error as text
+    Failed { id: String, error: DownloadError },
+    /// A cancelled or dismissed item left the queue
+    Removed { id: String },
+    /// The queue order or a priority changed; lists every item in its new order
+    Reordered { items: Vec<(String, Priority)> },
+}
+
+struct Inner {
+    queue: DownloadQueue,
+    max_parallel: AtomicUsize,
+    /// Attempts per item before a transient failure is final
+    max_attempts: AtomicU32,
+    /// Set once the app is closing; nothing new is started after that
+    stopping: AtomicBool,
+    /// Cancellation tokens of the items downloading right now
+    running: Mutex<HashMap<String, CancellationToken>>,
+    wake: Notify,
+    events: Sender<QueueEvent>,
+}
+
+/// Runs the pending items of a `DownloadQueue`, a limited number at a time.
+///
+/// Clones share the same queue and workers, so the UI can enqueue from any page.
+#[derive(Clone)]
+pub struct DownloadScheduler {
+    inner: Arc<Inner>,
+}
+
+impl DownloadScheduler {
+    /// Starts scheduling on the Tokio runtime; changes arrive on the returned receiver.
+    pub fn start(queue: DownloadQueue, max_parallel: usize) -> (Self, Receiver<QueueEvent>) {
+        let (events, receiver) = mpsc::channel();
+        let scheduler = Self {
+            inner: Arc::new(Inner {
+                queue,
+                max_parallel: AtomicUsize::new(max_parallel.max(1)),
+                max_attempts: AtomicU32::new(DEFAULT_MAX_ATTEMPTS),
+                stopping: AtomicBool::new(false),
+                running: Mutex::new(HashMap::new()),
+                wake: Notify::new(),
+                events,
+            }),
+        };
+
+        let worker = scheduler.clone();
+        tokio::spawn(async move { worker.run().await });
+
+        (scheduler, receiver)
+    }
+
+    /// Queues a download; without an output path it goes to the default download folder.
+    pub async fn enqueue(&self, mut request: DownloadRequest) -> String {
+        if request.output_path.is_none() {
+            request.output_path = Some(output_dir(&request));
+        }
+        let id = self.inner.queue.add(request.clone()).await;
+        info!("Queued {}", id);
+        self.emit(QueueEvent::Added {
+            item: Box::new(QueueItem {
+                id: id.clone(),
+                request,
+                status: DownloadStatus::Pending,
+                priority: Priority::Normal,
+                history: Vec::new(),
+                failed_attempts: 0,
+            }),
+        });
+        // Higher priority items already waiting stay ahead of it
+        self.reordered().await;
+        id
+    }
+
+    /// Moves an item within the queue; the next free slot goes by the new order.
+    pub async fn move_item(&self, id: &str, movement: QueueMove) {
+        if self.inner.queue.move_item(id, movement).await {
+            self.reordered().await;
+        }
+    }
+
+    pub async fn set_priority(&self, id: &str, priority: Priority) {
+        if self.inner.queue.set_priority(id, priority).await {
+            self.reordered().await;
+        }
+    }
+
+    async fn reordered(&self) {
+        let items = self
+            .inner
+            .queue
+            .list_all()
+            .await
+            .into_iter()
+            .map(|item| (item.id, item.priority))
+            .collect();
+        self.emit(QueueEvent::Reordered { items });
+        self.inner.wake.notify_one();
+    }
+
+    /// Changes how many items download at once; running ones are never stopped.
+    pub fn set_max_parallel(&self, max_parallel: usize) {
+        let max_parallel = max_parallel.max(1);
+        if self.inner.max_parallel.swap(max_parallel, Ordering::SeqCst) != max_parallel {
+            info!("Parallel downloads: {}", max_parallel);
+            self.inner.wake.notify_one();
+        }
+    }
+
+    /// Changes how many times an item is tried before a transient failure is final.
+    pub fn set_max_attempts(&self, max_attempts: u32) {
+        self.inner
+            .max_attempts
+            .store(max_attempts.max(1), Ordering::SeqCst);
+    }
+
+    /// Cancels the item if it is running, and drops it from the queue.
+    pub async fn remove(&self, id: &str) {
+        let running = self.running().get(id).cloned();
+        match running {
+            // The worker removes it once yt-dlp has stopped
+            Some(token) => token.cancel(),
+            None => {
+                self.inner.queue.remove(id).await;
+                self.emit(QueueEvent::Removed { id: id.to_string() });
+            }
+        }
+    }
+
+    /// Stops the item, keeping its partial files, and holds it until resumed.
+    pub async fn pause(&self, id: &str) {
+        let running = self.running().get(id).cloned();
+        match running {
+            // The worker marks it paused once yt-dlp has stopped
+            Some(token) => token.interrupt(),
+            None => self.mark_paused(id).await,
+        }
+    }
+
+    /// Puts a paused item back in line; yt-dlp continues its partial files.
+    pub async fn resume(&self, id: &str) {
+        if self.inner.queue.resume(id).await {
+            self.emit(QueueEvent::Status {
+                id: id.to_string(),
+                status: DownloadStatus::Pending,
+            });
+            self.inner.wake.notify_one();
+        }
+    }
+
+    pub async fn pause_all(&self) {
+        for item in self.inner.queue.list_all().await {
+            if matches!(
+                item.status,
+                DownloadStatus::Pending
+                    | DownloadStatus::Downloading { .. }
+                    | DownloadStatus::Retrying { .. }
+            ) {
+                self.pause(&item.id).await;
+            }
+        }
+    }
+
+    pub async fn resume_all(&self) {
+        for item in self.inner.queue.list_all().await {
+            if matches!(item.status, DownloadStatus::Paused { .. }) {
+                self.resume(&item.id).await;
+            }
+        }
+    }
+
+    async fn mark_paused(&self, id: &str) {
+        if let Some(status) = self.inner.queue.pause(id).await {
+            info!("Paused {}", id);
+            self.emit(QueueEvent::Status {
+                id: id.to_string(),
+                status,
+            });
+        }
+    }
+
+    /// Stops running downloads so they resume next session, waiting up to `timeout`.
+    ///
+    /// Nothing is started afterwards; meant for when the app is about to exit.
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.inner.stopping.store(true, Ordering::SeqCst);
+        for token in self.running().values() {
+            token.interrupt();
+        }
+
+        let deadline = Instant::now() + timeout;
+        while !self.running().is_empty() {
+            if Instant::now() >= deadline {
+                warn!("Downloads still stopping at exit");
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    fn running(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancellationToken>> {
+        self.inner.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn emit(&self, event: QueueEvent) {
+        // Nobody listening any more just means the window is gone
+        let _ = self.inner.events.send(event);
+    }
+
+    async fn set_status(&self, id: &str, status: DownloadStatus) {
+        self.inner.queue.update_status(id, status.clone()).await;
+        self.emit(QueueEvent::Status {
+            id: id.to_string(),
+            status,
+        });
+    }
+
+    /// Looks for ready items again once `delay` has passed.
+    fn wake_after(&self, delay: Duration) {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            scheduler.inner.wake.notify_one();
+        });
+    }
+
+    async fn run(self) {
+        // Show what the previous session left behind
+        for item in self.inner.queue.list_all().await {
+            if let DownloadStatus::Retrying { next_attempt, .. } = &item.status {
+                let delay = next_attempt
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                self.wake_after(delay);
+            }
+            self.emit(QueueEvent::Added {
+                item: Box::new(item),
+            });
+        }
+
+        loop {
+            self.start_ready().await;
+            self.inner.wake.notified().await;
+        }
+    }
+
+    /// Starts pending items while slots are free.
+    async fn start_ready(&self) {
+        if self.inner.stopping.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let free = self
+            .inner
+            .max_parallel
+            .load(Ordering::SeqCst)
+            .saturating_sub(self.running().len());
+        if free == 0 {
+            return;
+        }
+
+        for item in self.inner.queue.next_pending(free).await {
+            let cancel = CancellationToken::new();
+            self.running().insert(item.id.clone(), cancel.clone());
+            // Paused or removed since it was picked
+            if !self.inner.queue.start(&item.id).await {
+                self.running().remove(&item.id);
+                continue;
+            }
+            self.emit(QueueEvent::Status {
+                id: item.id.clone(),
+                status: DownloadStatus::Downloading { progress: 0.0 },
+            });
+
+            let scheduler = self.clone();
+            tokio::spawn(async move { scheduler.run_item(item, cancel).await });
+        }
+    }
+
+    async fn run_item(self, item: QueueItem, cancel: CancellationToken) {
+        let id = item.id.clone();
+        info!("Starting {}: {}", id, item.request.url);
+
+        // Progress arrives on yt-dlp reader threads; statuses are updated from here
+        let (progress_sender, mut progress_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let events = self.inner.events.clone();
+        let progress_id = id.clone();
+        let report = move |fraction: Option<f32>, text: String| {
+            let _ = progress_sender.send(fraction);
+            let _ = events.send(QueueEvent::Progress {
+                id: progress_id.clone(),
+                fraction,
+                text,
+            });
+        };
+
+        let queue = self.inner.queue.clone();
+        let status_id = id.clone();
+        let tracker = tokio::spawn(async move {
+            while let Some(fraction) = progress_receiver.recv().await {
+                if let Some(progress) = fraction {
+                    queue
+                        .update_status(&status_id, DownloadStatus::Downloading { progress })
+                        .await;
+                }
+            }
+        });
+
+        let result = Self::download(item.request, cancel.clone(), report).await;
+        tracker.abort();
+        let _ = tracker.await;
+        self.running().remove(&id);
+
+        match result {
+            Ok(status) => {
+                info!("Finished {}", id);
+                self.set_status(&id, status).await;
+            }
+            Err(DownloadError::Cancelled) if cancel.keeps_partial_files() => {
+                if self.inner.stopping.load(Ordering::SeqCst) {
+                    // The journal still says downloading, so the next session resumes it
+                    info!("Interrupted {}", id);
+                } else {
+                    self.mark_paused(&id).await;
+                }
+            }
+            Err(DownloadError::Cancelled) => {
+                info!("Cancelled {}", id);
+                self.inner.queue.remove(&id).await;
+                self.emit(QueueEvent::Removed { id: id.clone() });
+            }
+            Err(e) => {
+                let failures = self.inner.queue.record_failure(&id, e.to_string()).await;
+                warn!("{} failed (attempt {}): {}", id, failures, e);
+
+                let max_attempts = self.inner.max_attempts.load(Ordering::SeqCst);
+                if retry::should_retry(&e, failures, max_attempts) {
+                    let delay = retry::backoff_delay(failures);
+                    info!("Retrying {} in {}s", id, delay.as_secs());
+                    let status = DownloadStatus::Retrying {
+                        attempt: failures,
+                        max_attempts,
+                        next_attempt: SystemTime::now() + delay,
+                        error: e.to_string(),
+                    };
+                    self.set_status(&id, status).await;
+
+                    self.wake_after(delay);
+                } else {
+                    let status = DownloadStatus::Failed {
+                        error: e.to_string(),
+                    };
+                    self.inner.queue.update_status(&id, status).await;
+                    self.emit(QueueEvent::Failed { id, error: e });
+                }
+            }
+        }
+
+        self.inner.wake.notify_one();
+    }
+
+    async fn download<F>(
+        request: DownloadRequest,
+        cancel: CancellationToken,
+        report: F,
+    ) -> Result<DownloadStatus>
+    where
+        F: Fn(Option<f32>, String) + Send + Sync + 'static,
+    {
+        let downloader = VideoDownloader::new(output_dir(&request));
+
+        if VideoDownloader::is_playlist_url(&request.url) {
+            downloader
+                .download_playlist(request, cancel, move |p| {
+                    let text = format!(
+                        "{}/{} • {} • {}",
+                        p.position,
+                        p.total,
+                        p.entry.title,
+                        p.progress.summary()
+                    );
+                    report(Some(p.overall), text);
+                })
+                .await
+                .map(|result| DownloadStatus::PlaylistCompleted {
+                    result: Box::new(result),
+                })
+        } else {
+            downloader
+                .download(request, cancel, move |p| report(p.fraction(), p.summary()))
+                .await
+                .map(|outcome| DownloadStatus::Completed {
+                    outcome: Box::new(outcome),
+                })
+        }
+    }
+}
+
+/// The folder `request` downloads into, never the directory the app was started from.
+fn output_dir(request: &DownloadRequest) -> String {
+    request
+        .output_path
+        .clone()
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| default_download_dir().to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remove_pending_item() {
+        let queue = DownloadQueue::new();
+        // No free slot, so nothing is started
+        let (scheduler, events) = DownloadScheduler::start(queue.clone(), 1);
+        scheduler
+            .running()
+            .insert("busy".to_string(), CancellationToken::new());
+
+        let id = scheduler.enqueue(DownloadRequest::default()).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let item = queue.get(&id).await.unwrap();
+        assert!(matches!(item.status, DownloadStatus::Pending));
+        assert_eq!(
+            item.request.output_path,
+            Some(default_download_dir().to_string_lossy().into_owned())
+        );
+
+        scheduler.remove(&id).await;
+        assert!(queue.get(&id).await.is_none());
+        let removed = std::iter::from_fn(|| events.recv_timeout(Duration::from_secs(1)).ok())
+            .any(|event| matches!(event, QueueEvent::Removed { id: removed } if removed == id));
+        assert!(removed);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_resume_pending_item() {
+        let queue = DownloadQueue::new();
+        let (scheduler, _events) = DownloadScheduler::start(queue.clone(), 1);
+        scheduler
+            .running()
+            .insert("busy".to_string(), CancellationToken::new());
+
+        let first = scheduler.enqueue(DownloadRequest::default()).await;
+        let second = scheduler.enqueue(DownloadRequest::default()).await;
+        scheduler.pause_all().await;
+        for id in [&first, &second] {
+            assert!(matches!(
+                queue.get(id).await.unwrap().status,
+                DownloadStatus::Paused { .. }
+            ));
+        }
+
+        scheduler.resume(&second).await;
+        let item = queue.get(&second).await.unwrap();
+        assert!(matches!(item.status, DownloadStatus::Pending));
+        assert!(item.request.resume);
+        assert!(matches!(
+            queue.get(&first).await.unwrap().status,
+            DownloadStatus::Paused { .. }
+        ));
+    }
+}
@@ -11,7 +11,9 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+use crate::core::auth;
 use crate::core::downloader::{Platform, VideoDownloader};
+use crate::core::network;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
@@ -101,6 +103,7 @@ impl SearchService {
             return Self::execute_search_command(
                 &candidate_url,
                 &["--dump-json", "--flat-playlist", "--skip-download"],
+                VideoDownloader::detect_platform(&candidate_url),
                 Some(limit),
             )
             .await;
@@ -116,6 +119,7 @@ impl SearchService {
             Self::execute_search_command(
                 &yt_expr,
                 &["--dump-json", "--flat-playlist", "--skip-download"],
+                Platform::YouTube,
                 None, // Limit is embedded in ytsearch prefix
             )
             .await
@@ -128,6 +132,7 @@ impl SearchService {
             Self::execute_search_command(
                 &dzen_url,
                 &["--dump-json", "--flat-playlist", "--skip-download"],
+                Platform::Dzen,
                 Some(dzen_limit),
             )
             .await
@@ -140,8 +145,8 @@ impl SearchService {
             Self::search_rutube(&rutube_query, rutube_limit).await
         }));
 
-        // Note: VK does not support search without authentication.
-        // VK API requires access tokens which is beyond scope for a simple downloader.
+        // Note: VK search needs API access tokens, which cookies cannot provide;
+        // signed-in VK profiles still apply to pasted VK URLs above.
 
         let mut aggregated_results = Vec::new();
         let mut errors = Vec::new();
@@ -181,10 +186,14 @@ impl SearchService {
     async fn execute_search_command(
         input: &str,
         args: &[&str],
+        platform: Platform,
         limit: Option<u32>,
     ) -> Result<Vec<SearchResult>, SearchError> {
         let mut cmd = Command::new("yt-dlp");
-        cmd.arg(input).args(args);
+        cmd.arg(input)
+            .args(args)
+            .args(network::resolve_yt_dlp_args(input).await)
+            .args(auth::yt_dlp_args(&platform));
 
         if let Some(l) = limit {
             cmd.arg("--playlist-items").arg(format!("1-{}", l));
@@ -220,7 +229,7 @@ impl SearchService {
 
         debug!("Searching Rutube with URL: {}", url);
 
-        let response = reqwest::get(&url)
+        let response = network::send(network::http_client_for(&url).await.get(&url))
             .await
             .map_err(|e| SearchError::IoError(format!("Rutube API request failed: {}", e)))?;
 
@@ -231,12 +240,11 @@ impl SearchService {
             )));
         }
 
-        let body = response
-            .text()
+        let body = network::read_body(response)
             .await
             .map_err(|e| SearchError::IoError(format!("Failed to read Rutube response: {}", e)))?;
 
-        parse_rutube_results(&body)
+        parse_rutube_results(&String::from_utf8_lossy(&body))
     }
 }
 
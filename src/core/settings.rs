@@ -0,0 +1,244 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  settings.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 14:40 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 14:40 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::conflict::ConflictPolicy;
+use crate::core::downloader::Platform;
+use crate::core::embed::EmbedOptions;
+use crate::core::error::{DownloadError, Result};
+use crate::core::network::NetworkSettings;
+use crate::core::retry::DEFAULT_MAX_ATTEMPTS;
+use crate::core::scheduler::DEFAULT_PARALLEL_DOWNLOADS;
+use crate::core::sponsorblock::SponsorBlockOptions;
+use crate::core::template::DEFAULT_TEMPLATE;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "vdownloader";
+const SETTINGS_FILE: &str = "settings.json";
+
+/// User preferences that outlive a session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// Output filename template per platform; missing platforms use the default
+    pub filename_templates: HashMap<Platform, String>,
+    /// What to do when a download would replace an existing file
+    pub conflict_policy: ConflictPolicy,
+    /// Tags, cover art and chapters to embed into every download
+    pub embed: EmbedOptions,
+    /// SponsorBlock categories and server for YouTube downloads
+    pub sponsorblock: SponsorBlockOptions,
+    /// Speed cap shared by all downloads, in bytes per second
+    pub speed_limit: Option<u64>,
+    /// Proxy, headers and timeouts for all network access
+    pub network: NetworkSettings,
+    /// How many queued downloads run at the same time
+    pub parallel_downloads: usize,
+    /// Tries per queued download when it fails on network errors or rate limits
+    pub max_attempts: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            filename_templates: HashMap::new(),
+            conflict_policy: ConflictPolicy::default(),
+            embed: EmbedOptions::default(),
+            sponsorblock: SponsorBlockOptions::default(),
+            speed_limit: None,
+            network: NetworkSettings::default(),
+            parallel_downloads: DEFAULT_PARALLEL_DOWNLOADS,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings file, falling back to defaults when it is missing or unreadable.
+    pub fn load() -> Self {
+        Self::load_from(&settings_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable settings {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&settings_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| DownloadError::IoError(format!("Failed to encode settings: {}", e)))?;
+        write_atomically(path, contents.as_bytes())?;
+        info!("Saved settings to {}", path.display());
+        Ok(())
+    }
+
+    pub fn filename_template(&self, platform: &Platform) -> &str {
+        self.filename_templates
+            .get(platform)
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_TEMPLATE)
+    }
+
+    pub fn set_filename_template(&mut self, platform: Platform, template: &str) {
+        if template == DEFAULT_TEMPLATE {
+            self.filename_templates.remove(&platform);
+        } else {
+            self.filename_templates
+                .insert(platform, template.to_string());
+        }
+    }
+}
+
+/// Per-user configuration directory, following the XDG base directory spec.
+pub fn config_dir() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", ".config").join(APP_DIR)
+}
+
+/// Per-user data directory for state the app keeps, following the XDG base directory spec.
+pub fn data_dir() -> PathBuf {
+    base_dir("XDG_DATA_HOME", ".local/share").join(APP_DIR)
+}
+
+/// Where downloads go when no folder was picked: ~/Videos, or the home folder without one.
+pub fn default_download_dir() -> PathBuf {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let videos = home.join("Videos");
+    if videos.is_dir() {
+        videos
+    } else {
+        home
+    }
+}
+
+fn settings_path() -> PathBuf {
+    config_dir().join(SETTINGS_FILE)
+}
+
+fn base_dir(xdg_var: &str, home_fallback: &str) -> PathBuf {
+    if let Some(dir) = std::env::var_os(xdg_var).filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    #[cfg(windows)]
+    if let Some(dir) = std::env::var_os("APPDATA") {
+        return PathBuf::from(dir);
+    }
+
+    std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(home_fallback))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Writes `contents` to a temporary sibling and renames it over `path`, so a
+/// crash never leaves a half-written file behind.
+///
+/// Both the file and the rename are flushed to disk before returning, so after
+/// a power loss `path` holds either the old or the new contents.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            DownloadError::IoError(format!("Failed to create {}: {}", parent.display(), e))
+        })?;
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let write_error = |e: std::io::Error| {
+        DownloadError::IoError(format!("Failed to write {}: {}", tmp_path.display(), e))
+    };
+    let mut file = std::fs::File::create(&tmp_path).map_err(write_error)?;
+    file.write_all(contents).map_err(write_error)?;
+    file.sync_all().map_err(write_error)?;
+    drop(file);
+
+    std::fs::rename(&tmp_path, path).map_err(|e| {
+        DownloadError::IoError(format!("Failed to replace {}: {}", path.display(), e))
+    })?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// Makes a rename in `path`'s directory durable.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+        return;
+    };
+    if let Err(e) = std::fs::File::open(parent).and_then(|dir| dir.sync_all()) {
+        warn!("Failed to sync {}: {}", parent.display(), e);
+    }
+}
+
+/// Directories cannot be opened for syncing here; the rename is flushed with the file system.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_per_platform() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.filename_template(&Platform::YouTube),
+            DEFAULT_TEMPLATE
+        );
+
+        settings.set_filename_template(Platform::YouTube, "%(uploader)s/%(title)s.%(ext)s");
+        assert_eq!(
+            settings.filename_template(&Platform::YouTube),
+            "%(uploader)s/%(title)s.%(ext)s"
+        );
+        assert_eq!(settings.filename_template(&Platform::Vk), DEFAULT_TEMPLATE);
+
+        settings.set_filename_template(Platform::YouTube, DEFAULT_TEMPLATE);
+        assert!(settings.filename_templates.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("vdownloader_settings_{}", std::process::id()));
+        let path = dir.join(SETTINGS_FILE);
+
+        let mut settings = Settings::default();
+        settings.set_filename_template(Platform::Rutube, "%(id)s.%(ext)s");
+        settings.save_to(&path).unwrap();
+
+        assert_eq!(Settings::load_from(&path), settings);
+        assert!(!dir.join("settings.json.tmp").exists());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(Settings::load_from(&path), Settings::default());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
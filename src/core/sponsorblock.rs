@@ -0,0 +1,290 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  sponsorblock.rs                                      TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 13 2025 14:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 13 2025 14:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use crate::core::network;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The public SponsorBlock server, also yt-dlp's default.
+pub const DEFAULT_API_URL: &str = "https://sponsor.ajay.app";
+
+const API_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Kinds of community-submitted YouTube segments.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum SponsorCategory {
+    Sponsor,
+    Intro,
+    Outro,
+    SelfPromo,
+}
+
+impl SponsorCategory {
+    pub const ALL: [SponsorCategory; 4] = [
+        SponsorCategory::Sponsor,
+        SponsorCategory::Intro,
+        SponsorCategory::Outro,
+        SponsorCategory::SelfPromo,
+    ];
+
+    /// Name used by the SponsorBlock API and yt-dlp.
+    pub fn api_name(self) -> &'static str {
+        match self {
+            SponsorCategory::Sponsor => "sponsor",
+            SponsorCategory::Intro => "intro",
+            SponsorCategory::Outro => "outro",
+            SponsorCategory::SelfPromo => "selfpromo",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SponsorCategory::Sponsor => "Sponsor",
+            SponsorCategory::Intro => "Intro",
+            SponsorCategory::Outro => "Outro",
+            SponsorCategory::SelfPromo => "Self-promotion",
+        }
+    }
+}
+
+/// What to do with the segments of one category.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SegmentAction {
+    #[default]
+    Keep,
+    /// Cut the segment out of the output
+    Remove,
+    /// Keep the segment but add a chapter for it
+    Mark,
+}
+
+impl SegmentAction {
+    pub const ALL: [SegmentAction; 3] = [
+        SegmentAction::Keep,
+        SegmentAction::Remove,
+        SegmentAction::Mark,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SegmentAction::Keep => "Keep",
+            SegmentAction::Remove => "Remove",
+            SegmentAction::Mark => "Mark as chapter",
+        }
+    }
+}
+
+/// SponsorBlock handling for YouTube downloads.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SponsorBlockOptions {
+    /// Categories missing here are kept as they are
+    pub actions: HashMap<SponsorCategory, SegmentAction>,
+    /// Server to query, such as a local mirror
+    pub api_url: String,
+}
+
+impl Default for SponsorBlockOptions {
+    fn default() -> Self {
+        Self {
+            actions: HashMap::new(),
+            api_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+}
+
+impl SponsorBlockOptions {
+    pub fn action(&self, category: SponsorCategory) -> SegmentAction {
+        self.actions.get(&category).copied().unwrap_or_default()
+    }
+
+    pub fn set_action(&mut self, category: SponsorCategory, action: SegmentAction) {
+        if action == SegmentAction::Keep {
+            self.actions.remove(&category);
+        } else {
+            self.actions.insert(category, action);
+        }
+    }
+
+    /// Whether any category is removed or marked.
+    pub fn is_active(&self) -> bool {
+        SponsorCategory::ALL
+            .iter()
+            .any(|c| self.action(*c) != SegmentAction::Keep)
+    }
+
+    /// Stops touching any segments, keeping the server setting.
+    pub fn disable(&mut self) {
+        self.actions.clear();
+    }
+
+    fn categories(&self, action: SegmentAction) -> Vec<&'static str> {
+        SponsorCategory::ALL
+            .iter()
+            .filter(|c| self.action(**c) == action)
+            .map(|c| c.api_name())
+            .collect()
+    }
+
+    /// yt-dlp options removing and marking the chosen categories.
+    pub fn yt_dlp_args(&self) -> Vec<String> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+
+        let mut args = Vec::new();
+        let remove = self.categories(SegmentAction::Remove);
+        if !remove.is_empty() {
+            args.extend(["--sponsorblock-remove".to_string(), remove.join(",")]);
+        }
+
+        let mark = self.categories(SegmentAction::Mark);
+        if !mark.is_empty() {
+            args.extend(["--sponsorblock-mark".to_string(), mark.join(",")]);
+            // Marks only reach the file as embedded chapters
+            args.push("--embed-chapters".to_string());
+        }
+
+        args.extend([
+            "--sponsorblock-api".to_string(),
+            self.api_base().to_string(),
+        ]);
+        args
+    }
+
+    fn api_base(&self) -> &str {
+        let url = self.api_url.trim().trim_end_matches('/');
+        if url.is_empty() {
+            DEFAULT_API_URL
+        } else {
+            url
+        }
+    }
+
+    /// Segment lookup in the form yt-dlp uses, for checking that the server answers.
+    ///
+    /// yt-dlp asks by the first characters of the video id's SHA-256; any
+    /// prefix will do here since only the response status matters.
+    fn probe_url(&self) -> String {
+        let categories = format!(
+            "[{}]",
+            SponsorCategory::ALL
+                .iter()
+                .map(|c| format!("\"{}\"", c.api_name()))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        format!(
+            "{}/api/skipSegments/0000?service=YouTube&categories={}",
+            self.api_base(),
+            urlencoding::encode(&categories)
+        )
+    }
+
+    /// Checks that the server answers before yt-dlp relies on it.
+    ///
+    /// yt-dlp aborts a download when the API cannot be reached, so callers
+    /// drop SponsorBlock handling with a warning when this fails.
+    pub async fn check_api(&self) -> Result<()> {
+        let url = self.probe_url();
+        let unreachable = |e: String| {
+            DownloadError::NetworkError(format!(
+                "SponsorBlock API at {} is unavailable: {}",
+                self.api_base(),
+                e
+            ))
+        };
+
+        let response = network::http_client_for(&url)
+            .await
+            .get(&url)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| unreachable(e.to_string()))?;
+
+        // 404 is how the API says there are no segments
+        let status = response.status();
+        if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(unreachable(format!("status {}", status)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_sponsorblock_args() {
+        let mut options = SponsorBlockOptions::default();
+        assert!(options.yt_dlp_args().is_empty());
+
+        options.set_action(SponsorCategory::Outro, SegmentAction::Remove);
+        options.set_action(SponsorCategory::Sponsor, SegmentAction::Remove);
+        options.set_action(SponsorCategory::Intro, SegmentAction::Mark);
+        options.api_url = "http://localhost:8080/".to_string();
+
+        assert_eq!(
+            options.yt_dlp_args(),
+            vec![
+                "--sponsorblock-remove",
+                "sponsor,outro",
+                "--sponsorblock-mark",
+                "intro",
+                "--embed-chapters",
+                "--sponsorblock-api",
+                "http://localhost:8080"
+            ]
+        );
+        assert!(options
+            .probe_url()
+            .starts_with("http://localhost:8080/api/skipSegments/0000?service=YouTube"));
+
+        options.set_action(SponsorCategory::Intro, SegmentAction::Keep);
+        assert_eq!(options.action(SponsorCategory::Intro), SegmentAction::Keep);
+        options.disable();
+        assert!(!options.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_check_api() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for status in ["404 Not Found", "500 Internal Server Error"] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = socket.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let options = SponsorBlockOptions {
+            api_url: format!("http://{}", address),
+            ..Default::default()
+        };
+        assert!(options.check_api().await.is_ok());
+        assert!(options.check_api().await.is_err());
+    }
+}
@@ -0,0 +1,215 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  subtitles.rs                                         TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 12 2025 10:15 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 12 2025 10:15 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use crate::core::format::{AudioExtraction, Container, FormatSpec};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    #[default]
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+        }
+    }
+}
+
+/// A subtitle language a video offers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleTrack {
+    pub language: String,
+    pub name: Option<String>,
+    /// Generated by the site's speech recognition or machine translation
+    pub automatic: bool,
+    /// Formats the site serves, such as "vtt" or "srv3"
+    pub formats: Vec<String>,
+}
+
+impl SubtitleTrack {
+    /// Label such as "ru - Russian (auto-generated)".
+    pub fn label(&self) -> String {
+        let mut label = match self.name {
+            Some(ref name) if name != &self.language => format!("{} - {}", self.language, name),
+            _ => self.language.clone(),
+        };
+        if self.automatic {
+            label.push_str(" (auto-generated)");
+        }
+        label
+    }
+}
+
+/// Subtitles to fetch along with a download.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubtitleOptions {
+    pub languages: Vec<String>,
+    /// Fall back to auto-generated subtitles for languages without manual ones
+    pub automatic: bool,
+    pub format: SubtitleFormat,
+    /// Mux the subtitles into the video instead of keeping side files
+    pub embed: bool,
+}
+
+impl SubtitleOptions {
+    fn embeds_srt(&self, audio: Option<&AudioExtraction>) -> bool {
+        self.embed
+            && !self.languages.is_empty()
+            && self.format == SubtitleFormat::Srt
+            && audio.is_none()
+    }
+
+    /// Whether a webm download is remuxed to mkv so it can hold SRT subtitles.
+    ///
+    /// WebM only carries WebVTT, and yt-dlp skips other formats without
+    /// failing; like cover art, this only happens when no container was chosen.
+    pub fn remuxes_webm(&self, format: &FormatSpec, audio: Option<&AudioExtraction>) -> bool {
+        self.embeds_srt(audio) && format.container.is_none()
+    }
+
+    /// yt-dlp options that download, convert and optionally embed the subtitles.
+    pub fn yt_dlp_args(&self, format: &FormatSpec, audio: Option<&AudioExtraction>) -> Vec<String> {
+        if self.languages.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ext = self.format.extension();
+        if self.embeds_srt(audio) && format.container == Some(Container::Webm) {
+            warn!("WebM cannot hold SRT subtitles, embedding them as VTT");
+            ext = SubtitleFormat::Vtt.extension();
+        }
+
+        let mut args = vec!["--write-subs".to_string()];
+        if self.automatic {
+            args.push("--write-auto-subs".to_string());
+        }
+        args.extend([
+            "--sub-langs".to_string(),
+            self.languages.join(","),
+            "--sub-format".to_string(),
+            format!("{}/best", ext),
+            "--convert-subs".to_string(),
+            ext.to_string(),
+        ]);
+
+        if self.embed {
+            args.push("--embed-subs".to_string());
+        }
+
+        args
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSubtitleInfo {
+    subtitles: Option<HashMap<String, Vec<RawSubtitle>>>,
+    automatic_captions: Option<HashMap<String, Vec<RawSubtitle>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSubtitle {
+    ext: Option<String>,
+    name: Option<String>,
+}
+
+/// Parses the manual and auto-generated subtitles from yt-dlp's `-J` output.
+///
+/// Manual tracks come first; YouTube's live chat replay is not a subtitle and is dropped.
+pub fn parse_subtitles(json: &str) -> Result<Vec<SubtitleTrack>> {
+    let raw: RawSubtitleInfo = serde_json::from_str(json)
+        .map_err(|e| DownloadError::ExtractionError(format!("Invalid subtitle list: {}", e)))?;
+
+    let tracks = |map: Option<HashMap<String, Vec<RawSubtitle>>>, automatic: bool| {
+        let mut tracks: Vec<SubtitleTrack> = map
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(language, _)| language != "live_chat")
+            .map(|(language, entries)| SubtitleTrack {
+                name: entries.iter().find_map(|e| e.name.clone()),
+                formats: entries.into_iter().filter_map(|e| e.ext).collect(),
+                language,
+                automatic,
+            })
+            .collect();
+        tracks.sort_by(|a, b| a.language.cmp(&b.language));
+        tracks
+    };
+
+    let mut all = tracks(raw.subtitles, false);
+    all.extend(tracks(raw.automatic_captions, true));
+    Ok(all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subtitles() {
+        let json = r#"{
+            "subtitles": {
+                "ru": [{"ext": "vtt", "name": "Russian"}, {"ext": "srt"}],
+                "en": [{"ext": "vtt", "name": "English"}],
+                "live_chat": [{"ext": "json"}]
+            },
+            "automatic_captions": {"de": [{"ext": "srv3", "name": "German"}]}
+        }"#;
+
+        let tracks = parse_subtitles(json).unwrap();
+        let languages: Vec<&str> = tracks.iter().map(|t| t.language.as_str()).collect();
+        assert_eq!(languages, ["en", "ru", "de"]);
+        assert_eq!(tracks[1].formats, ["vtt", "srt"]);
+        assert_eq!(tracks[2].label(), "de - German (auto-generated)");
+
+        assert!(parse_subtitles(r#"{"subtitles": null}"#)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_subtitle_args() {
+        assert!(SubtitleOptions::default()
+            .yt_dlp_args(&FormatSpec::default(), None)
+            .is_empty());
+
+        let options = SubtitleOptions {
+            languages: vec!["ru".to_string(), "en".to_string()],
+            automatic: true,
+            format: SubtitleFormat::Srt,
+            embed: true,
+        };
+        let args = options.yt_dlp_args(&FormatSpec::default(), None);
+
+        assert!(args.windows(2).any(|w| w == ["--sub-langs", "ru,en"]));
+        assert!(args.windows(2).any(|w| w == ["--convert-subs", "srt"]));
+        assert!(args.contains(&"--write-auto-subs".to_string()));
+        assert!(args.contains(&"--embed-subs".to_string()));
+        assert!(options.remuxes_webm(&FormatSpec::default(), None));
+
+        let webm = FormatSpec {
+            container: Some(Container::Webm),
+            ..Default::default()
+        };
+        assert!(!options.remuxes_webm(&webm, None));
+        let args = options.yt_dlp_args(&webm, None);
+        assert!(args.windows(2).any(|w| w == ["--convert-subs", "vtt"]));
+    }
+}
@@ -0,0 +1,269 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  template.rs                                          TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 14:10 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 14:10 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::error::{DownloadError, Result};
+use std::collections::HashMap;
+
+/// Template used when the user has not configured one.
+pub const DEFAULT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// Longest file name most filesystems accept, in bytes.
+pub const MAX_NAME_BYTES: usize = 255;
+
+/// Length yt-dlp trims names to, leaving room for the extension and
+/// intermediate suffixes such as `.f137` or `.part-Frag12`.
+pub const TRIM_FILENAME_CHARS: usize = 200;
+
+/// Checks that a template stays inside the download directory and names a file.
+pub fn validate_template(template: &str) -> Result<()> {
+    let invalid =
+        |reason: &str| DownloadError::InvalidTemplate(format!("{} ({})", reason, template));
+    let trimmed = template.trim();
+
+    if trimmed.is_empty() {
+        return Err(invalid("template is empty"));
+    }
+
+    let bytes = trimmed.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if trimmed.starts_with('/')
+        || trimmed.starts_with('\\')
+        || trimmed.starts_with('~')
+        || has_drive
+    {
+        return Err(invalid(
+            "template must be relative to the download directory",
+        ));
+    }
+
+    if trimmed
+        .split(['/', '\\'])
+        .any(|component| component.trim() == "..")
+    {
+        return Err(invalid("template must not leave the download directory"));
+    }
+
+    if trimmed.matches("%(").count() != count_fields(trimmed) {
+        return Err(invalid("unterminated %(...) field"));
+    }
+
+    if !trimmed.contains("%(ext)s") {
+        return Err(invalid("template must contain %(ext)s"));
+    }
+
+    Ok(())
+}
+
+fn count_fields(template: &str) -> usize {
+    let mut count = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find("%(") {
+        match rest[start..].find(')') {
+            Some(end) => {
+                count += 1;
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    count
+}
+
+/// Renders `template` the way yt-dlp would, using `fields` as sample metadata.
+///
+/// Supports `%(a,b|default)s` style alternatives and zero padded numbers such
+/// as `%(playlist_index)03d`. Missing fields render as `NA`, like yt-dlp.
+pub fn render_template(template: &str, fields: &HashMap<&str, String>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('%') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        if let Some(stripped) = after.strip_prefix('%') {
+            rendered.push('%');
+            rest = stripped;
+            continue;
+        }
+
+        let Some(inner) = after.strip_prefix('(') else {
+            rendered.push('%');
+            rest = after;
+            continue;
+        };
+
+        let Some(close) = inner.find(')') else {
+            rendered.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let spec = &inner[..close];
+        let tail = &inner[close + 1..];
+        let conversion_len = tail
+            .find(|c: char| c.is_ascii_alphabetic())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let conversion = &tail[..conversion_len];
+
+        let (names, default) = match spec.split_once('|') {
+            Some((names, default)) => (names, Some(default)),
+            None => (spec, None),
+        };
+        let value = names
+            .split(',')
+            .map(|name| name.split(['>', '&', '.']).next().unwrap_or("").trim())
+            .find_map(|name| fields.get(name).cloned())
+            .or_else(|| default.map(str::to_string))
+            .unwrap_or_else(|| "NA".to_string());
+
+        rendered.push_str(&sanitize_component(&pad_number(&value, conversion)));
+        rest = &tail[conversion_len..];
+    }
+    rendered.push_str(rest);
+
+    rendered
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .map(|component| truncate_name(component, MAX_NAME_BYTES))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn pad_number(value: &str, conversion: &str) -> String {
+    let Some(width) = conversion
+        .strip_suffix('d')
+        .and_then(|w| w.strip_prefix('0'))
+        .and_then(|w| w.parse::<usize>().ok())
+    else {
+        return value.to_string();
+    };
+
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        format!("{:0>width$}", value, width = width)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Makes a metadata value safe to use as part of a single path component.
+pub fn sanitize_component(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let trimmed = cleaned.trim();
+    if trimmed.chars().all(|c| c == '.') {
+        trimmed.replace('.', "_")
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Shortens a file name to `max_bytes`, keeping its extension when it has one.
+pub fn truncate_name(name: &str, max_bytes: usize) -> String {
+    if name.len() <= max_bytes {
+        return name.to_string();
+    }
+
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 && name.len() - dot <= 16 => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    };
+
+    let budget = max_bytes.saturating_sub(ext.len());
+    let mut end = budget.min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}{}", stem[..end].trim_end(), ext)
+}
+
+/// Sample metadata for previewing templates in the UI.
+pub fn sample_fields(ext: &str) -> HashMap<&'static str, String> {
+    HashMap::from([
+        ("title", "Example Video: Part 1/2".to_string()),
+        ("id", "dQw4w9WgXcQ".to_string()),
+        ("uploader", "Example Channel".to_string()),
+        ("channel", "Example Channel".to_string()),
+        ("upload_date", "20251211".to_string()),
+        ("duration", "212".to_string()),
+        ("height", "1080".to_string()),
+        ("resolution", "1920x1080".to_string()),
+        ("playlist", "Example Playlist".to_string()),
+        ("playlist_title", "Example Playlist".to_string()),
+        ("playlist_index", "3".to_string()),
+        ("extractor", "youtube".to_string()),
+        ("ext", ext.to_string()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template(DEFAULT_TEMPLATE).is_ok());
+        assert!(
+            validate_template("%(uploader)s/%(upload_date)s - %(title)s [%(id)s].%(ext)s").is_ok()
+        );
+
+        assert!(validate_template("").is_err());
+        assert!(validate_template("/tmp/%(title)s.%(ext)s").is_err());
+        assert!(validate_template("C:\\%(title)s.%(ext)s").is_err());
+        assert!(validate_template("../%(title)s.%(ext)s").is_err());
+        assert!(validate_template("a/../../%(title)s.%(ext)s").is_err());
+        assert!(validate_template("%(title)s").is_err());
+        assert!(validate_template("%(title.%(ext)s").is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let fields = sample_fields("mp4");
+
+        assert_eq!(
+            render_template(
+                "%(uploader)s/%(upload_date)s - %(title)s [%(id)s].%(ext)s",
+                &fields
+            ),
+            "Example Channel/20251211 - Example Video_ Part 1_2 [dQw4w9WgXcQ].mp4"
+        );
+        assert_eq!(
+            render_template(
+                "%(playlist_index)03d %(artist,uploader)s %(album|No album)s 100%%.%(ext)s",
+                &fields
+            ),
+            "003 Example Channel No album 100%.mp4"
+        );
+        assert_eq!(render_template("%(missing)s.%(ext)s", &fields), "NA.mp4");
+    }
+
+    #[test]
+    fn test_truncate_and_sanitize() {
+        let long = format!("{}.mkv", "é".repeat(200));
+        let truncated = truncate_name(&long, MAX_NAME_BYTES);
+        assert!(truncated.len() <= MAX_NAME_BYTES);
+        assert!(truncated.ends_with(".mkv"));
+
+        assert_eq!(sanitize_component(".."), "__");
+        assert_eq!(sanitize_component(" a/b\\c "), "a_b_c");
+    }
+}
@@ -0,0 +1,57 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  conflict_dialog.rs                                   TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 16:45 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 16:45 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::conflict::{ConflictPolicy, FileConflict};
+use gtk4::{AlertDialog, Window};
+
+/// Choices offered by the dialog, in button order; the last one cancels.
+const CHOICES: [Option<ConflictPolicy>; 4] = [
+    Some(ConflictPolicy::Skip),
+    Some(ConflictPolicy::Overwrite),
+    Some(ConflictPolicy::Rename),
+    None,
+];
+
+/// Asks how to handle a file that already exists.
+///
+/// Returns the policy to download with, or `None` if the user cancelled.
+pub async fn ask_conflict_policy(
+    parent: Option<&Window>,
+    conflict: &FileConflict,
+) -> Option<ConflictPolicy> {
+    let file_name = conflict
+        .path()
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| conflict.path().display().to_string());
+
+    let dialog = AlertDialog::builder()
+        .modal(true)
+        .message(format!("\"{}\" already exists", file_name))
+        .detail(format!(
+            "Existing: {}\nIncoming: {}\n\n{}",
+            conflict.describe_existing(),
+            conflict.describe_incoming(),
+            conflict.path().display()
+        ))
+        .buttons(["Skip", "Overwrite", "Keep both", "Cancel"])
+        .default_button(0)
+        .cancel_button(3)
+        .build();
+
+    let choice = dialog.choose_future(parent).await.ok()?;
+    CHOICES
+        .get(usize::try_from(choice).ok()?)
+        .copied()
+        .flatten()
+}
@@ -5,38 +5,462 @@
 /*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
 /*                                                          TT         SS II */
 /*  Created: Dec 07 2025 13:36 st93642                      TT    SSSSSSS II */
-/*  Updated: Dec 07 2025 13:37 st93642                                       */
+/*  Updated: Dec 16 2025 10:15 st93642                                       */
 /*                                                                           */
 /*   Transport and Telecommunication Institute - Riga, Latvia                */
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
-use gtk4::{prelude::*, Box, Frame, Label, Orientation, ScrolledWindow};
+use crate::core::downloader::DownloadStatus;
+use crate::core::outcome::DownloadOutcome;
+use crate::core::queue::{Priority, QueueItem, QueueMove};
+use crate::ui::components::download_summary::create_download_summary;
+use gtk4::{
+    gdk, prelude::*, Box, Button, DragSource, DropDown, DropTarget, Frame, Label, Orientation,
+    ProgressBar, ScrolledWindow, WidgetPaintable,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
 
-pub fn create_queue_placeholder() -> Frame {
-    let frame = Frame::builder()
-        .label("Download Queue")
-        .margin_top(12)
-        .build();
+/// What the user did to a queued item.
+#[derive(Debug, Clone)]
+pub enum QueueAction {
+    /// Cancel or dismiss it
+    Remove,
+    Pause,
+    Resume,
+    Move(QueueMove),
+    SetPriority(Priority),
+}
+
+/// Called with the id of the item the user acted on.
+pub type ActionCallback = std::boxed::Box<dyn Fn(&str, QueueAction)>;
+
+/// Called when the user pauses (`true`) or resumes (`false`) the whole queue.
+pub type PauseAllCallback = std::boxed::Box<dyn Fn(bool)>;
+
+/// Widgets of one queued download.
+struct QueueRow {
+    container: Box,
+    progress_bar: ProgressBar,
+    status_label: Label,
+    button: Button,
+    pause_button: Button,
+    paused: Rc<Cell<bool>>,
+    /// Priority and position controls, shown while the item waits
+    controls: Box,
+    priority_dropdown: DropDown,
+}
+
+pub struct DownloadQueueView {
+    pub container: Frame,
+    queue_box: Box,
+    placeholder_label: Label,
+    rows: RefCell<HashMap<String, QueueRow>>,
+    on_action: Rc<RefCell<Option<ActionCallback>>>,
+    on_pause_all: Rc<RefCell<Option<PauseAllCallback>>>,
+}
+
+fn emit(on_action: &Rc<RefCell<Option<ActionCallback>>>, id: &str, action: QueueAction) {
+    if let Some(callback) = on_action.borrow().as_ref() {
+        callback(id, action);
+    }
+}
+
+impl DownloadQueueView {
+    pub fn new() -> Self {
+        let frame = Frame::builder()
+            .label("Download Queue")
+            .margin_top(12)
+            .build();
+
+        let scrolled_window = ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .vscrollbar_policy(gtk4::PolicyType::Automatic)
+            .min_content_height(200)
+            .build();
+
+        let toolbar = Box::new(Orientation::Horizontal, 6);
+        toolbar.set_halign(gtk4::Align::End);
+        toolbar.set_margin_top(6);
+        toolbar.set_margin_end(12);
+        let pause_all_button = Button::with_label("Pause all");
+        let resume_all_button = Button::with_label("Resume all");
+        toolbar.append(&pause_all_button);
+        toolbar.append(&resume_all_button);
+
+        let queue_box = Box::new(Orientation::Vertical, 6);
+        queue_box.set_margin_top(12);
+        queue_box.set_margin_bottom(12);
+        queue_box.set_margin_start(12);
+        queue_box.set_margin_end(12);
+
+        let placeholder_label = Label::new(Some("No downloads in queue"));
+        placeholder_label.add_css_class("dim-label");
+
+        queue_box.append(&placeholder_label);
+        scrolled_window.set_child(Some(&queue_box));
+
+        let content = Box::new(Orientation::Vertical, 0);
+        content.append(&toolbar);
+        content.append(&scrolled_window);
+        frame.set_child(Some(&content));
+
+        let on_pause_all: Rc<RefCell<Option<PauseAllCallback>>> = Rc::new(RefCell::new(None));
+        for (button, pause) in [(&pause_all_button, true), (&resume_all_button, false)] {
+            let on_pause_all = on_pause_all.clone();
+            button.connect_clicked(move |_| {
+                if let Some(callback) = on_pause_all.borrow().as_ref() {
+                    callback(pause);
+                }
+            });
+        }
+
+        Self {
+            container: frame,
+            queue_box,
+            placeholder_label,
+            rows: RefCell::new(HashMap::new()),
+            on_action: Rc::new(RefCell::new(None)),
+            on_pause_all,
+        }
+    }
+
+    pub fn set_pause_all_callback<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        *self.on_pause_all.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
+    pub fn set_action_callback<F>(&self, callback: F)
+    where
+        F: Fn(&str, QueueAction) + 'static,
+    {
+        *self.on_action.borrow_mut() = Some(std::boxed::Box::new(callback));
+    }
+
+    /// Shows a newly queued download at the end of the list.
+    pub fn add_item(&self, item: &QueueItem) {
+        let id = item.id.as_str();
+        if self.rows.borrow().contains_key(id) {
+            return;
+        }
+        self.placeholder_label.set_visible(false);
+
+        let container = Box::new(Orientation::Vertical, 0);
+        let card = Box::new(Orientation::Vertical, 4);
+        card.add_css_class("card");
+
+        let header = Box::new(Orientation::Horizontal, 12);
+        header.set_margin_top(8);
+        header.set_margin_start(12);
+        header.set_margin_end(12);
 
-    let scrolled_window = ScrolledWindow::builder()
-        .hscrollbar_policy(gtk4::PolicyType::Never)
-        .vscrollbar_policy(gtk4::PolicyType::Automatic)
-        .min_content_height(200)
-        .build();
+        let title_label = Label::new(Some(&item.request.url));
+        title_label.add_css_class("heading");
+        title_label.set_halign(gtk4::Align::Start);
+        title_label.set_hexpand(true);
+        title_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
 
-    let queue_box = Box::new(Orientation::Vertical, 6);
-    queue_box.set_margin_top(12);
-    queue_box.set_margin_bottom(12);
-    queue_box.set_margin_start(12);
-    queue_box.set_margin_end(12);
+        let button = Button::with_label("Cancel");
+        button.add_css_class("flat");
 
-    let placeholder_label = Label::new(Some("No downloads in queue"));
-    placeholder_label.add_css_class("dim-label");
+        let controls = Box::new(Orientation::Horizontal, 2);
+        let labels: Vec<&str> = Priority::ALL.iter().map(|p| p.label()).collect();
+        let priority_dropdown = DropDown::from_strings(&labels);
+        priority_dropdown.set_tooltip_text(Some("Priority"));
+        priority_dropdown.set_valign(gtk4::Align::Center);
+        set_dropdown_priority(&priority_dropdown, item.priority);
+        controls.append(&priority_dropdown);
 
-    queue_box.append(&placeholder_label);
-    scrolled_window.set_child(Some(&queue_box));
-    frame.set_child(Some(&scrolled_window));
+        let moves = [
+            ("go-up-symbolic", "Move up", QueueMove::Up),
+            ("go-down-symbolic", "Move down", QueueMove::Down),
+            ("go-top-symbolic", "Move to top", QueueMove::Top),
+            (
+                "media-skip-forward-symbolic",
+                "Download next",
+                QueueMove::Next,
+            ),
+        ];
+        for (icon, tooltip, movement) in moves {
+            let move_button = Button::from_icon_name(icon);
+            move_button.add_css_class("flat");
+            move_button.set_tooltip_text(Some(tooltip));
+            let on_action = self.on_action.clone();
+            let id_clone = id.to_string();
+            move_button.connect_clicked(move |_| {
+                emit(&on_action, &id_clone, QueueAction::Move(movement.clone()));
+            });
+            controls.append(&move_button);
+        }
+
+        let pause_button = Button::from_icon_name("media-playback-pause-symbolic");
+        pause_button.add_css_class("flat");
+        pause_button.set_tooltip_text(Some("Pause"));
+        let paused = Rc::new(Cell::new(false));
+
+        header.append(&title_label);
+        header.append(&controls);
+        header.append(&pause_button);
+        header.append(&button);
+
+        let progress_bar = ProgressBar::builder().show_text(true).build();
+        progress_bar.set_margin_start(12);
+        progress_bar.set_margin_end(12);
+        progress_bar.set_visible(false);
+
+        let status_label = Label::new(Some("Waiting for a free slot"));
+        status_label.add_css_class("dim-label");
+        status_label.add_css_class("caption");
+        status_label.set_halign(gtk4::Align::Start);
+        status_label.set_wrap(true);
+        status_label.set_margin_start(12);
+        status_label.set_margin_end(12);
+        status_label.set_margin_bottom(8);
+
+        card.append(&header);
+        card.append(&progress_bar);
+        card.append(&status_label);
+        container.append(&card);
+        self.queue_box.append(&container);
+
+        let on_action = self.on_action.clone();
+        let id_clone = id.to_string();
+        button.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            emit(&on_action, &id_clone, QueueAction::Remove);
+        });
+
+        let on_action = self.on_action.clone();
+        let id_clone = id.to_string();
+        let paused_clone = paused.clone();
+        pause_button.connect_clicked(move |button| {
+            // Enabled again once the new status arrives
+            button.set_sensitive(false);
+            let action = if paused_clone.get() {
+                QueueAction::Resume
+            } else {
+                QueueAction::Pause
+            };
+            emit(&on_action, &id_clone, action);
+        });
+
+        let on_action = self.on_action.clone();
+        let id_clone = id.to_string();
+        priority_dropdown.connect_selected_notify(move |dropdown| {
+            if let Some(priority) = Priority::ALL.get(dropdown.selected() as usize) {
+                emit(&on_action, &id_clone, QueueAction::SetPriority(*priority));
+            }
+        });
+
+        // Waiting items can be dragged onto another one to take its place
+        let drag_source = DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        let controls_clone = controls.clone();
+        let id_clone = id.to_string();
+        drag_source.connect_prepare(move |_, _, _| {
+            controls_clone
+                .is_visible()
+                .then(|| gdk::ContentProvider::for_value(&id_clone.to_value()))
+        });
+        let card_clone = card.clone();
+        drag_source.connect_drag_begin(move |source, _| {
+            source.set_icon(Some(&WidgetPaintable::new(Some(&card_clone))), 0, 0);
+        });
+        card.add_controller(drag_source);
+
+        let drop_target = DropTarget::new(String::static_type(), gdk::DragAction::MOVE);
+        let on_action = self.on_action.clone();
+        let id_clone = id.to_string();
+        drop_target.connect_drop(move |_, value, _, _| {
+            let Ok(dragged) = value.get::<String>() else {
+                return false;
+            };
+            emit(
+                &on_action,
+                &dragged,
+                QueueAction::Move(QueueMove::Before(id_clone.clone())),
+            );
+            true
+        });
+        container.add_controller(drop_target);
+
+        self.rows.borrow_mut().insert(
+            id.to_string(),
+            QueueRow {
+                container,
+                progress_bar,
+                status_label,
+                button,
+                pause_button,
+                paused,
+                controls,
+                priority_dropdown,
+            },
+        );
+    }
+
+    /// Puts the rows in the given order and updates their priorities.
+    pub fn reorder(&self, items: &[(String, Priority)]) {
+        let rows = self.rows.borrow();
+        let mut previous: gtk4::Widget = self.placeholder_label.clone().upcast();
+        for (id, priority) in items {
+            let Some(row) = rows.get(id) else {
+                continue;
+            };
+            self.queue_box
+                .reorder_child_after(&row.container, Some(&previous));
+            set_dropdown_priority(&row.priority_dropdown, *priority);
+            previous = row.container.clone().upcast();
+        }
+    }
+
+    pub fn set_progress(&self, id: &str, fraction: Option<f32>, text: &str) {
+        if let Some(row) = self.rows.borrow().get(id) {
+            match fraction {
+                Some(fraction) => row.progress_bar.set_fraction(fraction as f64),
+                None => row.progress_bar.pulse(),
+            }
+            row.progress_bar.set_text(Some(text));
+        }
+    }
+
+    pub fn set_status(&self, id: &str, status: &DownloadStatus) {
+        let rows = self.rows.borrow();
+        let Some(row) = rows.get(id) else {
+            return;
+        };
+
+        let waiting = matches!(
+            status,
+            DownloadStatus::Pending
+                | DownloadStatus::Paused { .. }
+                | DownloadStatus::Retrying { .. }
+        );
+        row.controls.set_visible(waiting);
+        let paused = matches!(status, DownloadStatus::Paused { .. });
+        row.paused.set(paused);
+        row.pause_button.set_sensitive(true);
+        row.pause_button
+            .set_visible(!matches!(status, DownloadStatus::Failed { .. }));
+        if paused {
+            row.pause_button
+                .set_icon_name("media-playback-start-symbolic");
+            row.pause_button.set_tooltip_text(Some("Resume"));
+        } else {
+            row.pause_button
+                .set_icon_name("media-playback-pause-symbolic");
+            row.pause_button.set_tooltip_text(Some("Pause"));
+        }
+
+        match status {
+            DownloadStatus::Pending => {
+                row.progress_bar.set_visible(false);
+                row.status_label.set_label("Waiting for a free slot");
+            }
+            DownloadStatus::Downloading { progress } => {
+                row.progress_bar.set_visible(true);
+                row.progress_bar.set_fraction(*progress as f64);
+                row.status_label.set_label("Downloading");
+            }
+            DownloadStatus::Paused { progress } => {
+                row.progress_bar.set_visible(*progress > 0.0);
+                row.progress_bar.set_fraction(*progress as f64);
+                row.progress_bar.set_text(None);
+                row.status_label.set_label("Paused");
+            }
+            DownloadStatus::Retrying {
+                attempt,
+                max_attempts,
+                next_attempt,
+                error,
+            } => {
+                let wait = next_attempt
+                    .duration_since(std::time::SystemTime::now())
+                    .unwrap_or_default();
+                row.progress_bar.set_visible(false);
+                row.status_label.set_label(&format!(
+                    "Attempt {} of {} failed, trying again in {}s: {}",
+                    attempt,
+                    max_attempts,
+                    wait.as_secs(),
+                    error
+                ));
+            }
+            DownloadStatus::Completed { outcome } => {
+                self.show_finished(row, None, std::slice::from_ref(outcome.as_ref()));
+            }
+            DownloadStatus::PlaylistCompleted { result } => {
+                let message = format!(
+                    "Playlist \"{}\": {} downloaded, {} failed",
+                    result.title,
+                    result.downloads.len(),
+                    result.failed.len()
+                );
+                self.show_finished(row, Some(&message), &result.downloads);
+            }
+            DownloadStatus::Failed { error } => {
+                row.progress_bar.set_visible(false);
+                row.status_label.remove_css_class("dim-label");
+                row.status_label.add_css_class("error");
+                row.status_label.set_label(error);
+                row.button.set_label("Remove");
+                row.button.set_sensitive(true);
+            }
+        }
+    }
+
+    /// Replaces the row's progress with summaries of the finished downloads.
+    fn show_finished(&self, row: &QueueRow, message: Option<&str>, outcomes: &[DownloadOutcome]) {
+        if let Some(header) = row.button.parent().and_downcast::<Box>() {
+            header.remove(&row.button);
+        }
+        while let Some(child) = row.container.first_child() {
+            row.container.remove(&child);
+        }
+
+        if let Some(message) = message {
+            let message_label = Label::new(Some(message));
+            message_label.add_css_class("heading");
+            message_label.set_halign(gtk4::Align::Start);
+            message_label.set_wrap(true);
+            row.container.append(&message_label);
+        }
+        for outcome in outcomes {
+            row.container.append(&create_download_summary(outcome));
+        }
+
+        row.button.set_label("Remove from list");
+        row.button.set_sensitive(true);
+        row.button.set_halign(gtk4::Align::End);
+        row.container.append(&row.button);
+    }
+
+    pub fn remove(&self, id: &str) {
+        if let Some(row) = self.rows.borrow_mut().remove(id) {
+            self.queue_box.remove(&row.container);
+        }
+        if self.rows.borrow().is_empty() {
+            self.placeholder_label.set_visible(true);
+        }
+    }
+}
+
+fn set_dropdown_priority(dropdown: &DropDown, priority: Priority) {
+    let position = Priority::ALL
+        .iter()
+        .position(|p| *p == priority)
+        .unwrap_or_default() as u32;
+    if dropdown.selected() != position {
+        dropdown.set_selected(position);
+    }
+}
 
-    frame
+impl Default for DownloadQueueView {
+    fn default() -> Self {
+        Self::new()
+    }
 }
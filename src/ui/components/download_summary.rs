@@ -0,0 +1,157 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  download_summary.rs                                  TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 11 2025 11:30 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 11 2025 11:30 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::outcome::DownloadOutcome;
+use gtk4::{gio, prelude::*, Box, Button, Label, Orientation};
+use log::warn;
+use std::path::{Path, PathBuf};
+
+/// Card describing a finished download, with buttons to open it.
+pub fn create_download_summary(outcome: &DownloadOutcome) -> Box {
+    let card = Box::new(Orientation::Vertical, 4);
+    card.set_margin_top(8);
+    card.set_margin_bottom(8);
+    card.add_css_class("card");
+
+    let title_label = Label::new(Some(&outcome.title));
+    title_label.add_css_class("heading");
+    title_label.set_halign(gtk4::Align::Start);
+    title_label.set_wrap(true);
+    title_label.set_margin_top(8);
+    title_label.set_margin_start(12);
+    title_label.set_margin_end(12);
+    card.append(&title_label);
+
+    let details_label = Label::new(Some(&outcome.details()));
+    details_label.add_css_class("dim-label");
+    details_label.set_halign(gtk4::Align::Start);
+    details_label.set_wrap(true);
+    details_label.set_margin_start(12);
+    details_label.set_margin_end(12);
+    card.append(&details_label);
+
+    if outcome.skipped {
+        let skipped_label = Label::new(Some(
+            "The file already existed, so it was kept and nothing was downloaded",
+        ));
+        skipped_label.add_css_class("warning");
+        skipped_label.add_css_class("caption");
+        skipped_label.set_halign(gtk4::Align::Start);
+        skipped_label.set_wrap(true);
+        skipped_label.set_margin_start(12);
+        skipped_label.set_margin_end(12);
+        card.append(&skipped_label);
+    }
+
+    for warning in &outcome.warnings {
+        let warning_label = Label::new(Some(warning));
+        warning_label.add_css_class("warning");
+        warning_label.add_css_class("caption");
+        warning_label.set_halign(gtk4::Align::Start);
+        warning_label.set_wrap(true);
+        warning_label.set_margin_start(12);
+        warning_label.set_margin_end(12);
+        card.append(&warning_label);
+    }
+
+    let path_label = Label::new(Some(&outcome.primary_file.display().to_string()));
+    path_label.add_css_class("caption");
+    path_label.set_halign(gtk4::Align::Start);
+    path_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    path_label.set_selectable(true);
+    path_label.set_margin_start(12);
+    path_label.set_margin_end(12);
+    card.append(&path_label);
+
+    if !outcome.chapter_files.is_empty() {
+        let chapters_label = Label::new(Some(&format!(
+            "Split into {} chapter file(s)",
+            outcome.chapter_files.len()
+        )));
+        chapters_label.add_css_class("dim-label");
+        chapters_label.add_css_class("caption");
+        chapters_label.set_halign(gtk4::Align::Start);
+        chapters_label.set_margin_start(12);
+        chapters_label.set_tooltip_text(Some(
+            &outcome
+                .chapter_files
+                .iter()
+                .filter_map(|f| f.file_name())
+                .map(|f| f.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ));
+        card.append(&chapters_label);
+    }
+
+    if !outcome.extra_files.is_empty() {
+        let extra_label = Label::new(Some(&format!(
+            "{} more file(s) alongside",
+            outcome.extra_files.len()
+        )));
+        extra_label.add_css_class("dim-label");
+        extra_label.add_css_class("caption");
+        extra_label.set_halign(gtk4::Align::Start);
+        extra_label.set_margin_start(12);
+        extra_label.set_tooltip_text(Some(
+            &outcome
+                .extra_files
+                .iter()
+                .map(|f| f.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ));
+        card.append(&extra_label);
+    }
+
+    let button_box = Box::new(Orientation::Horizontal, 6);
+    button_box.set_margin_top(4);
+    button_box.set_margin_bottom(8);
+    button_box.set_margin_start(12);
+
+    let open_label = if outcome.primary_file.is_dir() {
+        "Open folder"
+    } else {
+        "Open file"
+    };
+    let open_button = Button::with_label(open_label);
+    let path = outcome.primary_file.clone();
+    open_button.connect_clicked(move |button| launch_file(button, &path, false));
+
+    let folder_button = Button::with_label("Show in folder");
+    let path = outcome.primary_file.clone();
+    folder_button.connect_clicked(move |button| launch_file(button, &path, true));
+
+    button_box.append(&open_button);
+    button_box.append(&folder_button);
+    card.append(&button_box);
+
+    card
+}
+
+fn launch_file(button: &Button, path: &Path, containing_folder: bool) {
+    let launcher = gtk4::FileLauncher::new(Some(&gio::File::for_path(path)));
+    let window = button.root().and_downcast::<gtk4::Window>();
+    let shown: PathBuf = path.to_path_buf();
+    let on_done = move |result: Result<(), gtk4::glib::Error>| {
+        if let Err(e) = result {
+            warn!("Failed to open {}: {}", shown.display(), e);
+        }
+    };
+
+    if containing_folder {
+        launcher.open_containing_folder(window.as_ref(), gio::Cancellable::NONE, on_done);
+    } else {
+        launcher.launch(window.as_ref(), gio::Cancellable::NONE, on_done);
+    }
+}
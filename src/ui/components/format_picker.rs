@@ -0,0 +1,413 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  format_picker.rs                                     TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 09 2025 15:20 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 09 2025 15:20 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::downloader::VideoDownloader;
+use crate::core::format::{
+    AudioCodec, AudioExtraction, Container, FormatInfo, FormatSpec, VideoCodec,
+};
+use gtk4::{
+    prelude::*, Box, Button, CheckButton, DropDown, Entry, Label, Orientation, Spinner, StringList,
+};
+use log::{info, warn};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const HEIGHT_CHOICES: [(&str, Option<u32>); 7] = [
+    ("Best quality", None),
+    ("2160p", Some(2160)),
+    ("1440p", Some(1440)),
+    ("1080p", Some(1080)),
+    ("720p", Some(720)),
+    ("480p", Some(480)),
+    ("360p", Some(360)),
+];
+
+const CODEC_CHOICES: [(&str, Option<VideoCodec>); 4] = [
+    ("Any codec", None),
+    ("AV1", Some(VideoCodec::Av1)),
+    ("VP9", Some(VideoCodec::Vp9)),
+    ("H.264", Some(VideoCodec::H264)),
+];
+
+const CONTAINER_CHOICES: [(&str, Option<Container>); 4] = [
+    ("Auto container", None),
+    ("MP4", Some(Container::Mp4)),
+    ("MKV", Some(Container::Mkv)),
+    ("WebM", Some(Container::Webm)),
+];
+
+const AUDIO_CODEC_CHOICES: [(&str, Option<AudioCodec>); 5] = [
+    ("Keep original audio", None),
+    ("MP3", Some(AudioCodec::Mp3)),
+    ("Opus", Some(AudioCodec::Opus)),
+    ("M4A", Some(AudioCodec::M4a)),
+    ("FLAC", Some(AudioCodec::Flac)),
+];
+
+const AUDIO_BITRATE_CHOICES: [(&str, Option<u32>); 5] = [
+    ("Default quality", None),
+    ("128 kbps", Some(128)),
+    ("192 kbps", Some(192)),
+    ("256 kbps", Some(256)),
+    ("320 kbps", Some(320)),
+];
+
+const AUTOMATIC_FORMAT: &str = "Automatic (use preferences above)";
+
+pub struct FormatPicker {
+    pub container: Box,
+    height_dropdown: DropDown,
+    codec_dropdown: DropDown,
+    container_dropdown: DropDown,
+    audio_only_check: CheckButton,
+    audio_codec_dropdown: DropDown,
+    audio_bitrate_dropdown: DropDown,
+    embed_audio_check: CheckButton,
+    formats_dropdown: DropDown,
+    formats: Rc<RefCell<Vec<FormatInfo>>>,
+}
+
+impl FormatPicker {
+    pub fn new(url_entry: &Entry) -> Self {
+        let container = Box::new(Orientation::Vertical, 6);
+        container.set_margin_top(12);
+
+        let quality_label = Label::new(Some("Quality:"));
+        quality_label.set_halign(gtk4::Align::Start);
+
+        let preferences_box = Box::new(Orientation::Horizontal, 12);
+
+        let height_dropdown = DropDown::from_strings(&HEIGHT_CHOICES.map(|(label, _)| label));
+        let codec_dropdown = DropDown::from_strings(&CODEC_CHOICES.map(|(label, _)| label));
+        let container_dropdown = DropDown::from_strings(&CONTAINER_CHOICES.map(|(label, _)| label));
+        let audio_only_check = CheckButton::with_label("Audio only");
+
+        preferences_box.append(&height_dropdown);
+        preferences_box.append(&codec_dropdown);
+        preferences_box.append(&container_dropdown);
+        preferences_box.append(&audio_only_check);
+
+        // Conversion options, shown only in audio mode
+        let audio_box = Box::new(Orientation::Horizontal, 12);
+        audio_box.set_visible(false);
+
+        let audio_codec_dropdown =
+            DropDown::from_strings(&AUDIO_CODEC_CHOICES.map(|(label, _)| label));
+        let audio_bitrate_dropdown =
+            DropDown::from_strings(&AUDIO_BITRATE_CHOICES.map(|(label, _)| label));
+        audio_bitrate_dropdown.set_sensitive(false);
+        let embed_audio_check = CheckButton::with_label("Embed tags and cover art");
+        embed_audio_check.set_active(true);
+        embed_audio_check.set_sensitive(false);
+
+        audio_box.append(&audio_codec_dropdown);
+        audio_box.append(&audio_bitrate_dropdown);
+        audio_box.append(&embed_audio_check);
+
+        let bitrate_clone = audio_bitrate_dropdown.clone();
+        let embed_clone = embed_audio_check.clone();
+        audio_codec_dropdown.connect_selected_notify(move |dropdown| {
+            let codec = AUDIO_CODEC_CHOICES
+                .get(dropdown.selected() as usize)
+                .and_then(|(_, c)| *c);
+            bitrate_clone.set_sensitive(codec.is_some_and(|c| !c.is_lossless()));
+            embed_clone.set_sensitive(codec.is_some());
+        });
+
+        let formats_box = Box::new(Orientation::Horizontal, 12);
+
+        let format_list = StringList::new(&[AUTOMATIC_FORMAT]);
+        let formats_dropdown = DropDown::builder()
+            .model(&format_list)
+            .hexpand(true)
+            .build();
+        formats_dropdown.set_tooltip_text(Some("Pick an exact format reported by the site"));
+
+        let load_button = Button::with_label("Show Formats");
+        let spinner = Spinner::new();
+        spinner.set_visible(false);
+
+        formats_box.append(&formats_dropdown);
+        formats_box.append(&spinner);
+        formats_box.append(&load_button);
+
+        let status_label = Label::new(None);
+        status_label.set_halign(gtk4::Align::Start);
+        status_label.add_css_class("dim-label");
+        status_label.add_css_class("caption");
+        status_label.set_visible(false);
+
+        container.append(&quality_label);
+        container.append(&preferences_box);
+        container.append(&audio_box);
+        container.append(&formats_box);
+        container.append(&status_label);
+
+        let formats = Rc::new(RefCell::new(Vec::new()));
+
+        // Manual preferences only matter while no exact format is picked
+        let height_clone = height_dropdown.clone();
+        let codec_clone = codec_dropdown.clone();
+        let container_clone = container_dropdown.clone();
+        let audio_clone = audio_only_check.clone();
+        formats_dropdown.connect_selected_notify(move |dropdown| {
+            let automatic = dropdown.selected() == 0;
+            let video_prefs = automatic && !audio_clone.is_active();
+            height_clone.set_sensitive(video_prefs);
+            codec_clone.set_sensitive(video_prefs);
+            container_clone.set_sensitive(video_prefs);
+            audio_clone.set_sensitive(automatic);
+        });
+
+        let height_clone = height_dropdown.clone();
+        let codec_clone = codec_dropdown.clone();
+        let container_clone = container_dropdown.clone();
+        let audio_box_clone = audio_box.clone();
+        audio_only_check.connect_toggled(move |check| {
+            let video_prefs = !check.is_active();
+            height_clone.set_sensitive(video_prefs);
+            codec_clone.set_sensitive(video_prefs);
+            container_clone.set_sensitive(video_prefs);
+            audio_box_clone.set_visible(check.is_active());
+        });
+
+        // A different URL invalidates the loaded list
+        let format_list_clone = format_list.clone();
+        let formats_clone = formats.clone();
+        let formats_dropdown_clone = formats_dropdown.clone();
+        let status_label_clone = status_label.clone();
+        url_entry.connect_changed(move |_| {
+            if formats_clone.borrow().is_empty() {
+                return;
+            }
+            formats_clone.borrow_mut().clear();
+            format_list_clone.splice(1, format_list_clone.n_items() - 1, &[]);
+            formats_dropdown_clone.set_selected(0);
+            status_label_clone.set_visible(false);
+        });
+
+        let url_entry_clone = url_entry.clone();
+        let formats_clone = formats.clone();
+        let formats_dropdown_clone = formats_dropdown.clone();
+        load_button.connect_clicked(move |btn| {
+            let url = url_entry_clone.text().to_string();
+            if url.is_empty() {
+                status_label.set_label("Enter a URL first to list its formats");
+                status_label.set_visible(true);
+                return;
+            }
+
+            btn.set_sensitive(false);
+            spinner.set_visible(true);
+            spinner.start();
+            status_label.set_label("Fetching available formats...");
+            status_label.set_visible(true);
+
+            let btn_clone = btn.clone();
+            let spinner_clone = spinner.clone();
+            let status_label_clone = status_label.clone();
+            let format_list_clone = format_list.clone();
+            let formats_clone2 = formats_clone.clone();
+            let formats_dropdown_clone2 = formats_dropdown_clone.clone();
+
+            gtk4::glib::spawn_future_local(async move {
+                let result = VideoDownloader::list_formats(&url).await;
+
+                btn_clone.set_sensitive(true);
+                spinner_clone.stop();
+                spinner_clone.set_visible(false);
+
+                match result {
+                    Ok(list) => {
+                        info!("Loaded {} formats for {}", list.len(), url);
+                        let labels: Vec<String> = list.iter().map(Self::describe_format).collect();
+                        let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+                        format_list_clone.splice(1, format_list_clone.n_items() - 1, &labels);
+                        formats_dropdown_clone2.set_selected(0);
+                        status_label_clone
+                            .set_label(&format!("{} format(s) available", list.len()));
+                        *formats_clone2.borrow_mut() = list;
+                    }
+                    Err(e) => {
+                        warn!("Failed to list formats: {}", e);
+                        status_label_clone.set_label(&format!("Could not list formats: {}", e));
+                    }
+                }
+            });
+        });
+
+        Self {
+            container,
+            height_dropdown,
+            codec_dropdown,
+            container_dropdown,
+            audio_only_check,
+            audio_codec_dropdown,
+            audio_bitrate_dropdown,
+            embed_audio_check,
+            formats_dropdown,
+            formats,
+        }
+    }
+
+    /// Calls `f` whenever a choice that affects the output file extension changes.
+    pub fn connect_extension_changed<F: Fn() + 'static>(&self, f: F) {
+        let f = Rc::new(f);
+        let f_clone = f.clone();
+        self.audio_only_check.connect_toggled(move |_| f_clone());
+        let f_clone = f.clone();
+        self.audio_codec_dropdown
+            .connect_selected_notify(move |_| f_clone());
+        self.container_dropdown
+            .connect_selected_notify(move |_| f());
+    }
+
+    /// Audio conversion settings, if audio mode with a target codec is selected.
+    pub fn audio_extraction(&self) -> Option<AudioExtraction> {
+        if !self.audio_only_check.is_active() {
+            return None;
+        }
+
+        let codec = AUDIO_CODEC_CHOICES
+            .get(self.audio_codec_dropdown.selected() as usize)
+            .and_then(|(_, c)| *c)?;
+
+        let embed = self.embed_audio_check.is_active();
+        Some(AudioExtraction {
+            codec,
+            bitrate_kbps: AUDIO_BITRATE_CHOICES
+                .get(self.audio_bitrate_dropdown.selected() as usize)
+                .and_then(|(_, b)| *b),
+            embed_tags: embed,
+            embed_cover: embed,
+        })
+    }
+
+    /// Builds the format spec from the current selection.
+    pub fn format_spec(&self) -> FormatSpec {
+        let picked = self.formats_dropdown.selected() as usize;
+        let format_id = picked
+            .checked_sub(1)
+            .and_then(|idx| self.formats.borrow().get(idx).map(FormatInfo::selector));
+
+        FormatSpec {
+            max_height: HEIGHT_CHOICES
+                .get(self.height_dropdown.selected() as usize)
+                .and_then(|(_, h)| *h),
+            container: CONTAINER_CHOICES
+                .get(self.container_dropdown.selected() as usize)
+                .and_then(|(_, c)| *c),
+            video_codec: CODEC_CHOICES
+                .get(self.codec_dropdown.selected() as usize)
+                .and_then(|(_, c)| *c),
+            audio_only: self.audio_only_check.is_active(),
+            format_id,
+        }
+    }
+
+    fn describe_format(format: &FormatInfo) -> String {
+        let mut parts = vec![format!("{} ({})", format.format_id, format.ext)];
+
+        match (format.width, format.height) {
+            (Some(w), Some(h)) => parts.push(format!("{}x{}", w, h)),
+            (None, Some(h)) => parts.push(format!("{}p", h)),
+            _ if !format.has_video() => parts.push("audio only".to_string()),
+            _ => {}
+        }
+
+        if let Some(fps) = format.fps {
+            parts.push(format!("{:.0}fps", fps));
+        }
+
+        let codecs: Vec<&str> = [format.vcodec.as_deref(), format.acodec.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|c| *c != "none")
+            .collect();
+        if !codecs.is_empty() {
+            parts.push(codecs.join(" + "));
+        }
+
+        if let Some(bitrate) = format.bitrate {
+            parts.push(format!("{:.0} kbps", bitrate));
+        }
+
+        if let Some(size) = format.filesize {
+            parts.push(format!("~{}", Self::format_size(size)));
+        }
+
+        parts.join(" • ")
+    }
+
+    fn format_size(bytes: u64) -> String {
+        const MIB: f64 = 1024.0 * 1024.0;
+        let bytes = bytes as f64;
+        if bytes >= 1024.0 * MIB {
+            format!("{:.1} GB", bytes / (1024.0 * MIB))
+        } else if bytes >= MIB {
+            format!("{:.1} MB", bytes / MIB)
+        } else {
+            format!("{:.0} KB", bytes / 1024.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(FormatPicker::format_size(512 * 1024), "512 KB");
+        assert_eq!(FormatPicker::format_size(15 * 1024 * 1024), "15.0 MB");
+        assert_eq!(FormatPicker::format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_describe_format() {
+        let video = FormatInfo {
+            format_id: "137".to_string(),
+            ext: "mp4".to_string(),
+            width: Some(1920),
+            height: Some(1080),
+            fps: Some(30.0),
+            vcodec: Some("avc1.640028".to_string()),
+            acodec: Some("none".to_string()),
+            bitrate: Some(4000.0),
+            filesize: Some(50 * 1024 * 1024),
+            note: None,
+        };
+        assert_eq!(
+            FormatPicker::describe_format(&video),
+            "137 (mp4) • 1920x1080 • 30fps • avc1.640028 • 4000 kbps • ~50.0 MB"
+        );
+
+        let audio = FormatInfo {
+            format_id: "140".to_string(),
+            ext: "m4a".to_string(),
+            width: None,
+            height: None,
+            fps: None,
+            vcodec: Some("none".to_string()),
+            acodec: Some("mp4a.40.2".to_string()),
+            bitrate: Some(129.5),
+            filesize: None,
+            note: None,
+        };
+        assert_eq!(
+            FormatPicker::describe_format(&audio),
+            "140 (m4a) • audio only • mp4a.40.2 • 130 kbps"
+        );
+    }
+}
@@ -11,6 +11,11 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+pub mod conflict_dialog;
 pub mod download_queue;
+pub mod download_summary;
+pub mod format_picker;
 pub mod preview_window;
 pub mod search_view;
+pub mod settings_dialog;
+pub mod subtitle_picker;
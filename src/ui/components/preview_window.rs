@@ -11,17 +11,29 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+use crate::core::auth;
+use crate::core::clip::{format_timestamp, TimeRange};
+use crate::core::network;
 use crate::core::search::SearchResult;
 use gtk4::{prelude::*, ApplicationWindow, Box, Button, Label, Orientation, Spinner, Video};
 use log::{debug, info, warn};
+use std::cell::Cell;
 use std::process::Command as StdCommand;
+use std::rc::Rc;
+
+/// Receives the range marked while watching a preview.
+pub type ClipCallback = Rc<dyn Fn(&SearchResult, TimeRange)>;
 
 pub struct PreviewWindow {
     window: ApplicationWindow,
 }
 
 impl PreviewWindow {
-    pub fn new(parent: &ApplicationWindow, result: &SearchResult) -> Self {
+    pub fn new(
+        parent: &ApplicationWindow,
+        result: &SearchResult,
+        on_clip: Option<ClipCallback>,
+    ) -> Self {
         info!("Creating preview window for: {}", result.title);
 
         let window = ApplicationWindow::builder()
@@ -135,6 +147,11 @@ impl PreviewWindow {
         button_box.set_halign(gtk4::Align::End);
         button_box.set_margin_top(12);
 
+        if let Some(on_clip) = on_clip {
+            let clip_box = Self::create_clip_controls(&window, &video, result, on_clip);
+            button_box.append(&clip_box);
+        }
+
         let close_button = Button::with_label("Close");
         let window_clone = window.clone();
         close_button.connect_clicked(move |_| {
@@ -153,6 +170,104 @@ impl PreviewWindow {
         self.window.present();
     }
 
+    /// In/out mark buttons that hand the marked range to `on_clip`.
+    fn create_clip_controls(
+        window: &ApplicationWindow,
+        video: &Video,
+        result: &SearchResult,
+        on_clip: ClipCallback,
+    ) -> Box {
+        let clip_box = Box::new(Orientation::Horizontal, 6);
+        clip_box.set_hexpand(true);
+
+        let marks_label = Label::new(Some("Mark a clip while watching"));
+        marks_label.add_css_class("dim-label");
+        marks_label.set_hexpand(true);
+        marks_label.set_halign(gtk4::Align::Start);
+
+        let mark_in_button = Button::with_label("Mark In");
+        let mark_out_button = Button::with_label("Mark Out");
+        let use_clip_button = Button::with_label("Use Clip");
+        use_clip_button.add_css_class("suggested-action");
+        use_clip_button.set_sensitive(false);
+
+        let marks: Rc<Cell<(Option<f64>, Option<f64>)>> = Rc::new(Cell::new((None, None)));
+
+        let show_marks = {
+            let marks = marks.clone();
+            let marks_label = marks_label.clone();
+            let use_clip_button = use_clip_button.clone();
+            move || {
+                let (mark_in, mark_out) = marks.get();
+                let describe = |mark: Option<f64>| mark.map(format_timestamp);
+                marks_label.set_label(&format!(
+                    "In {} • Out {}",
+                    describe(mark_in).unwrap_or_else(|| "start".to_string()),
+                    describe(mark_out).unwrap_or_else(|| "end".to_string())
+                ));
+                use_clip_button.set_sensitive(mark_in.is_some() || mark_out.is_some());
+            }
+        };
+
+        // Position of the playing stream in seconds
+        let position = {
+            let video = video.clone();
+            move || {
+                video
+                    .media_stream()
+                    .map(|stream| stream.timestamp() as f64 / 1_000_000.0)
+            }
+        };
+
+        let marks_clone = marks.clone();
+        let position_clone = position.clone();
+        let show_marks_clone = show_marks.clone();
+        mark_in_button.connect_clicked(move |_| {
+            let Some(at) = position_clone() else {
+                return;
+            };
+            let (_, mark_out) = marks_clone.get();
+            // An out point before the new in point no longer makes sense
+            marks_clone.set((Some(at), mark_out.filter(|out| *out > at)));
+            show_marks_clone();
+        });
+
+        let marks_clone = marks.clone();
+        let marks_label_clone = marks_label.clone();
+        mark_out_button.connect_clicked(move |_| {
+            let Some(at) = position() else {
+                return;
+            };
+            let (mark_in, _) = marks_clone.get();
+            if mark_in.is_some_and(|start| at <= start) {
+                marks_label_clone.set_label("The out point must come after the in point");
+                return;
+            }
+            marks_clone.set((mark_in, Some(at)));
+            show_marks();
+        });
+
+        let window_clone = window.clone();
+        let result_clone = result.clone();
+        use_clip_button.connect_clicked(move |_| {
+            let (mark_in, mark_out) = marks.get();
+            let range = TimeRange {
+                start: mark_in.unwrap_or(0.0),
+                end: mark_out,
+            };
+            info!("Clip {} marked for {}", range, result_clone.url);
+            on_clip(&result_clone, range);
+            window_clone.close();
+        });
+
+        clip_box.append(&marks_label);
+        clip_box.append(&mark_in_button);
+        clip_box.append(&mark_out_button);
+        clip_box.append(&use_clip_button);
+
+        clip_box
+    }
+
     async fn get_video_url(
         url: &str,
         platform: &crate::core::downloader::Platform,
@@ -208,12 +323,15 @@ impl PreviewWindow {
             let output = tokio::task::spawn_blocking({
                 let url = url.to_string();
                 let format = format.to_string();
+                let platform = platform.clone();
                 move || {
                     StdCommand::new("yt-dlp")
                         .arg("--get-url")
                         .arg("--format")
                         .arg(&format)
                         .arg("--no-warnings")
+                        .args(network::yt_dlp_args(&url))
+                        .args(auth::yt_dlp_args(&platform))
                         .arg(&url)
                         .output()
                 }
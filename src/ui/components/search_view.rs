@@ -11,8 +11,11 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
+use crate::core::archive::{ArchiveEntry, DownloadArchive};
+use crate::core::clip::TimeRange;
+use crate::core::network;
 use crate::core::search::{SearchError, SearchResult, SearchService};
-use crate::ui::components::preview_window::PreviewWindow;
+use crate::ui::components::preview_window::{ClipCallback, PreviewWindow};
 use gdk_pixbuf::{Pixbuf, PixbufLoader};
 use gtk4::{
     prelude::*, ApplicationWindow, Button, Image, Label, ListBox, Orientation, ScrolledWindow,
@@ -26,7 +29,8 @@ use std::rc::Rc;
 const THUMBNAIL_WIDTH: i32 = 120;
 const THUMBNAIL_HEIGHT: i32 = 90;
 
-type DownloadCallback = std::boxed::Box<dyn Fn(SearchResult)>;
+/// Receives the chosen result and whether to fetch it again despite the download archive.
+type DownloadCallback = std::boxed::Box<dyn Fn(SearchResult, bool)>;
 type ThumbnailCache = HashMap<String, Pixbuf>;
 
 pub struct SearchView {
@@ -39,6 +43,7 @@ pub struct SearchView {
     results_list: ListBox,
     thumbnail_cache: Rc<RefCell<ThumbnailCache>>,
     download_callback: Rc<RefCell<Option<DownloadCallback>>>,
+    clip_callback: Rc<RefCell<Option<ClipCallback>>>,
     window: Rc<RefCell<Option<ApplicationWindow>>>,
 }
 
@@ -97,6 +102,7 @@ impl SearchView {
 
         let thumbnail_cache = Rc::new(RefCell::new(HashMap::new()));
         let download_callback = Rc::new(RefCell::new(None));
+        let clip_callback = Rc::new(RefCell::new(None));
         let window = Rc::new(RefCell::new(None));
 
         let mut view = Self {
@@ -109,6 +115,7 @@ impl SearchView {
             results_list: results_list.clone(),
             thumbnail_cache: thumbnail_cache.clone(),
             download_callback: download_callback.clone(),
+            clip_callback: clip_callback.clone(),
             window: window.clone(),
         };
 
@@ -118,11 +125,19 @@ impl SearchView {
 
     pub fn set_download_callback<F>(&self, callback: F)
     where
-        F: Fn(SearchResult) + 'static,
+        F: Fn(SearchResult, bool) + 'static,
     {
         *self.download_callback.borrow_mut() = Some(std::boxed::Box::new(callback));
     }
 
+    /// Called with the range marked in a preview, to download just that part.
+    pub fn set_clip_callback<F>(&self, callback: F)
+    where
+        F: Fn(&SearchResult, TimeRange) + 'static,
+    {
+        *self.clip_callback.borrow_mut() = Some(Rc::new(callback));
+    }
+
     pub fn set_window(&self, window: ApplicationWindow) {
         *self.window.borrow_mut() = Some(window);
     }
@@ -136,6 +151,7 @@ impl SearchView {
         let search_service = self.search_service;
         let thumbnail_cache = self.thumbnail_cache.clone();
         let download_callback = self.download_callback.clone();
+        let clip_callback = self.clip_callback.clone();
         let window = self.window.clone();
 
         let perform_search = Rc::new(move || {
@@ -168,6 +184,7 @@ impl SearchView {
             let search_service_clone = search_service;
             let thumbnail_cache_clone = thumbnail_cache.clone();
             let download_callback_clone = download_callback.clone();
+            let clip_callback_clone = clip_callback.clone();
             let window_clone = window.clone();
             let query_clone = query.to_string();
 
@@ -189,10 +206,13 @@ impl SearchView {
                             status_label_clone.remove_css_class("warning");
                             status_label_clone.add_css_class("dim-label");
 
+                            let archive = DownloadArchive::load();
                             for result in results {
                                 let card = Self::create_result_card(
                                     &result,
+                                    archive.get_on_disk(&result.platform, &result.id),
                                     &download_callback_clone,
+                                    &clip_callback_clone,
                                     &window_clone,
                                 );
                                 results_list_clone.append(&card);
@@ -234,7 +254,9 @@ impl SearchView {
 
     fn create_result_card(
         result: &SearchResult,
+        archived: Option<&ArchiveEntry>,
         download_callback: &Rc<RefCell<Option<DownloadCallback>>>,
+        clip_callback: &Rc<RefCell<Option<ClipCallback>>>,
         window: &Rc<RefCell<Option<ApplicationWindow>>>,
     ) -> gtk4::Box {
         let card = gtk4::Box::new(Orientation::Horizontal, 12);
@@ -293,6 +315,15 @@ impl SearchView {
         platform_label.add_css_class("caption");
         info_box.append(&platform_label);
 
+        if let Some(entry) = archived {
+            let badge = Label::new(Some("Already downloaded"));
+            badge.set_halign(gtk4::Align::Start);
+            badge.add_css_class("success");
+            badge.add_css_class("caption");
+            badge.set_tooltip_text(Some(&entry.path.display().to_string()));
+            info_box.append(&badge);
+        }
+
         card.append(&info_box);
 
         // Button box for Preview and Download
@@ -304,24 +335,37 @@ impl SearchView {
 
         let result_clone_preview = result.clone();
         let window_clone = window.clone();
+        let clip_callback_clone = clip_callback.clone();
         preview_button.connect_clicked(move |_| {
             if let Some(ref parent_window) = *window_clone.borrow() {
-                let preview = PreviewWindow::new(parent_window, &result_clone_preview);
+                let preview = PreviewWindow::new(
+                    parent_window,
+                    &result_clone_preview,
+                    clip_callback_clone.borrow().clone(),
+                );
                 preview.present();
             }
         });
 
         button_box.append(&preview_button);
 
-        // Download button
-        let download_button = Button::with_label("Download");
-        download_button.add_css_class("suggested-action");
+        // Download button; archived videos need an explicit second download
+        let redownload = archived.is_some();
+        let download_button = if redownload {
+            let button = Button::with_label("Download again");
+            button.set_tooltip_text(Some("Fetch it again even though it was downloaded before"));
+            button
+        } else {
+            let button = Button::with_label("Download");
+            button.add_css_class("suggested-action");
+            button
+        };
 
         let result_clone = result.clone();
         let download_callback_clone = download_callback.clone();
         download_button.connect_clicked(move |_| {
             if let Some(ref callback) = *download_callback_clone.borrow() {
-                callback(result_clone.clone());
+                callback(result_clone.clone(), redownload);
             }
         });
 
@@ -374,7 +418,7 @@ impl SearchView {
     async fn fetch_thumbnail(url: &str) -> Result<Pixbuf, Box<dyn std::error::Error>> {
         debug!("Fetching thumbnail: {}", url);
 
-        let response = reqwest::get(url).await?;
+        let response = network::send(network::http_client_for(url).await.get(url)).await?;
 
         if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
             return Err("Rate limited (HTTP 429)".into());
@@ -384,7 +428,7 @@ impl SearchView {
             return Err(format!("HTTP error: {}", response.status()).into());
         }
 
-        let bytes = response.bytes().await?;
+        let bytes = network::read_body(response).await?;
 
         let loader = PixbufLoader::new();
         loader.write(&bytes)?;
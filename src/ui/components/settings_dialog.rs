@@ -0,0 +1,421 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  settings_dialog.rs                                   TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 13 2025 15:10 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 13 2025 15:10 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::auth::{AuthProfiles, AuthStatus, PLATFORMS};
+use crate::core::downloader::Platform;
+use crate::core::network::{self, format_headers, parse_headers, IpVersion, NetworkSettings};
+use crate::core::settings::Settings;
+use crate::core::sponsorblock::DEFAULT_API_URL;
+use gtk4::{
+    prelude::*, ApplicationWindow, Box, Button, CheckButton, DropDown, Entry, FileDialog, Label,
+    Orientation, ScrolledWindow, SpinButton, TextView,
+};
+use log::{info, warn};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Preferences that are set once rather than per download.
+pub struct SettingsDialog {
+    window: ApplicationWindow,
+}
+
+impl SettingsDialog {
+    pub fn new(parent: &ApplicationWindow, settings: Rc<RefCell<Settings>>) -> Self {
+        let window = ApplicationWindow::builder()
+            .transient_for(parent)
+            .modal(true)
+            .title("Settings")
+            .default_width(480)
+            .build();
+
+        let container = Box::new(Orientation::Vertical, 6);
+        container.set_margin_top(12);
+        container.set_margin_bottom(12);
+        container.set_margin_start(12);
+        container.set_margin_end(12);
+
+        let sponsorblock_label = Label::new(Some("SponsorBlock API server:"));
+        sponsorblock_label.set_halign(gtk4::Align::Start);
+
+        let api_box = Box::new(Orientation::Horizontal, 12);
+        let api_entry = Entry::builder()
+            .text(settings.borrow().sponsorblock.api_url.as_str())
+            .placeholder_text(DEFAULT_API_URL)
+            .hexpand(true)
+            .build();
+        let reset_button = Button::with_label("Default");
+        api_box.append(&api_entry);
+        api_box.append(&reset_button);
+
+        let api_hint = Label::new(Some(
+            "Used for YouTube downloads that remove or mark segments. \
+             Point it at a mirror if the public server is unreachable.",
+        ));
+        api_hint.add_css_class("dim-label");
+        api_hint.add_css_class("caption");
+        api_hint.set_halign(gtk4::Align::Start);
+        api_hint.set_wrap(true);
+
+        let current = settings.borrow().network.clone();
+
+        let network_label = Label::new(Some("Network:"));
+        network_label.set_halign(gtk4::Align::Start);
+        network_label.set_margin_top(12);
+
+        let proxy_box = Box::new(Orientation::Horizontal, 12);
+        let proxy_label = Label::new(Some("Proxy:"));
+        let proxy_entry = Entry::builder()
+            .text(current.proxy.as_deref().unwrap_or_default())
+            .placeholder_text("e.g. socks5://127.0.0.1:1080 or http://proxy:8080")
+            .hexpand(true)
+            .build();
+        proxy_box.append(&proxy_label);
+        proxy_box.append(&proxy_entry);
+
+        let system_proxy_check = CheckButton::with_label("Use the system proxy when none is set");
+        system_proxy_check.set_active(current.use_system_proxy);
+
+        let agent_box = Box::new(Orientation::Horizontal, 12);
+        let agent_label = Label::new(Some("User agent:"));
+        let agent_entry = Entry::builder()
+            .text(current.user_agent.as_deref().unwrap_or_default())
+            .placeholder_text("yt-dlp default")
+            .hexpand(true)
+            .build();
+        agent_box.append(&agent_label);
+        agent_box.append(&agent_entry);
+
+        let headers_label = Label::new(Some("Extra headers, one \"Name: Value\" per line:"));
+        headers_label.set_halign(gtk4::Align::Start);
+        let headers_view = TextView::new();
+        headers_view.set_monospace(true);
+        headers_view
+            .buffer()
+            .set_text(&format_headers(&current.headers));
+        let headers_scroll = ScrolledWindow::builder()
+            .child(&headers_view)
+            .min_content_height(60)
+            .build();
+        headers_scroll.add_css_class("frame");
+
+        let connection_box = Box::new(Orientation::Horizontal, 12);
+        let ip_label = Label::new(Some("Connect over:"));
+        let ip_dropdown = DropDown::from_strings(&IpVersion::ALL.map(|v| v.label()));
+        let ip_index = IpVersion::ALL
+            .iter()
+            .position(|v| *v == current.ip_version)
+            .unwrap_or_default();
+        ip_dropdown.set_selected(ip_index as u32);
+        let timeout_label = Label::new(Some("Socket timeout (s):"));
+        timeout_label.set_margin_start(12);
+        let timeout_spin = SpinButton::with_range(1.0, 600.0, 1.0);
+        timeout_spin.set_value(current.socket_timeout as f64);
+        connection_box.append(&ip_label);
+        connection_box.append(&ip_dropdown);
+        connection_box.append(&timeout_label);
+        connection_box.append(&timeout_spin);
+
+        let network_hint = Label::new(Some(
+            "Applies to downloads, searches, previews and thumbnails.",
+        ));
+        network_hint.add_css_class("dim-label");
+        network_hint.add_css_class("caption");
+        network_hint.set_halign(gtk4::Align::Start);
+        network_hint.set_wrap(true);
+
+        let error_label = Label::new(None);
+        error_label.add_css_class("error");
+        error_label.add_css_class("caption");
+        error_label.set_halign(gtk4::Align::Start);
+        error_label.set_visible(false);
+
+        let button_box = Box::new(Orientation::Horizontal, 6);
+        button_box.set_halign(gtk4::Align::End);
+        button_box.set_margin_top(12);
+        let cancel_button = Button::with_label("Cancel");
+        let save_button = Button::with_label("Save");
+        save_button.add_css_class("suggested-action");
+        button_box.append(&cancel_button);
+        button_box.append(&save_button);
+
+        container.append(&sponsorblock_label);
+        container.append(&api_box);
+        container.append(&api_hint);
+        container.append(&network_label);
+        container.append(&proxy_box);
+        container.append(&system_proxy_check);
+        container.append(&agent_box);
+        container.append(&headers_label);
+        container.append(&headers_scroll);
+        container.append(&connection_box);
+        container.append(&network_hint);
+        container.append(&Self::accounts_section(&window));
+        container.append(&error_label);
+        container.append(&button_box);
+        window.set_child(Some(&container));
+
+        let api_entry_clone = api_entry.clone();
+        reset_button.connect_clicked(move |_| {
+            api_entry_clone.set_text(DEFAULT_API_URL);
+        });
+
+        let window_clone = window.clone();
+        cancel_button.connect_clicked(move |_| window_clone.close());
+
+        let window_clone = window.clone();
+        save_button.connect_clicked(move |_| {
+            let api_url = api_entry.text().trim().trim_end_matches('/').to_string();
+            let api_url = if api_url.is_empty() {
+                DEFAULT_API_URL.to_string()
+            } else {
+                api_url
+            };
+
+            if !api_url.starts_with("http://") && !api_url.starts_with("https://") {
+                error_label.set_label("The API server must be an http:// or https:// address");
+                error_label.set_visible(true);
+                return;
+            }
+
+            let buffer = headers_view.buffer();
+            let headers = match parse_headers(&buffer.text(
+                &buffer.start_iter(),
+                &buffer.end_iter(),
+                false,
+            )) {
+                Ok(headers) => headers,
+                Err(e) => {
+                    error_label.set_label(&e.to_string());
+                    error_label.set_visible(true);
+                    return;
+                }
+            };
+
+            let text_or_none = |entry: &Entry| {
+                let text = entry.text().trim().to_string();
+                (!text.is_empty()).then_some(text)
+            };
+            let network_settings = NetworkSettings {
+                proxy: text_or_none(&proxy_entry),
+                use_system_proxy: system_proxy_check.is_active(),
+                user_agent: text_or_none(&agent_entry),
+                headers,
+                ip_version: IpVersion::ALL
+                    .get(ip_dropdown.selected() as usize)
+                    .copied()
+                    .unwrap_or_default(),
+                socket_timeout: timeout_spin.value_as_int().max(1) as u64,
+            };
+            if let Err(e) = network_settings.validate() {
+                error_label.set_label(&e.to_string());
+                error_label.set_visible(true);
+                return;
+            }
+
+            info!("Using SponsorBlock API at {}", api_url);
+            network::configure(network_settings.clone());
+            settings.borrow_mut().sponsorblock.api_url = api_url;
+            settings.borrow_mut().network = network_settings;
+            if let Err(e) = settings.borrow().save() {
+                warn!("Failed to save settings: {}", e);
+            }
+            window_clone.close();
+        });
+
+        Self { window }
+    }
+
+    /// Per-platform sign-in; changes take effect at once rather than on Save.
+    fn accounts_section(window: &ApplicationWindow) -> Box {
+        let section = Box::new(Orientation::Vertical, 6);
+        section.set_margin_top(12);
+
+        let accounts_label = Label::new(Some("Accounts:"));
+        accounts_label.set_halign(gtk4::Align::Start);
+        section.append(&accounts_label);
+
+        let profiles = Rc::new(RefCell::new(AuthProfiles::load()));
+
+        for platform in PLATFORMS {
+            let row = Box::new(Orientation::Horizontal, 6);
+
+            let name_label = Label::new(Some(&format!("{:?}", platform)));
+            name_label.set_width_chars(10);
+            name_label.set_xalign(0.0);
+
+            let status_label = Label::new(None);
+            status_label.set_hexpand(true);
+            status_label.set_xalign(0.0);
+            status_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+            status_label.add_css_class("caption");
+
+            let cookies_button = Button::with_label("Cookies file...");
+            cookies_button.set_tooltip_text(Some("Import a Netscape cookies.txt export"));
+            let browser_button = Button::with_label("Browser...");
+            browser_button.set_tooltip_text(Some(
+                "Read cookies from a Firefox or Chromium-based browser profile folder",
+            ));
+            let sign_out_button = Button::with_label("Sign out");
+
+            show_status(
+                &status_label,
+                &sign_out_button,
+                &profiles.borrow().status(&platform),
+            );
+
+            row.append(&name_label);
+            row.append(&status_label);
+            row.append(&cookies_button);
+            row.append(&browser_button);
+            row.append(&sign_out_button);
+            section.append(&row);
+
+            let window_clone = window.clone();
+            let profiles_clone = profiles.clone();
+            let status_clone = status_label.clone();
+            let sign_out_clone = sign_out_button.clone();
+            let platform_clone = platform.clone();
+            cookies_button.connect_clicked(move |_| {
+                let dialog = FileDialog::builder()
+                    .title("Import cookies.txt")
+                    .modal(true)
+                    .build();
+
+                let profiles = profiles_clone.clone();
+                let status_label = status_clone.clone();
+                let sign_out_button = sign_out_clone.clone();
+                let platform = platform_clone.clone();
+                dialog.open(
+                    Some(&window_clone),
+                    None::<&gtk4::gio::Cancellable>,
+                    move |result| {
+                        let Some(path) = result.ok().and_then(|file| file.path()) else {
+                            return;
+                        };
+                        let imported = profiles.borrow_mut().import_cookies(&platform, &path);
+                        apply_change(
+                            &profiles,
+                            &platform,
+                            imported,
+                            &status_label,
+                            &sign_out_button,
+                        );
+                    },
+                );
+            });
+
+            let window_clone = window.clone();
+            let profiles_clone = profiles.clone();
+            let status_clone = status_label.clone();
+            let sign_out_clone = sign_out_button.clone();
+            let platform_clone = platform.clone();
+            browser_button.connect_clicked(move |_| {
+                let dialog = FileDialog::builder()
+                    .title("Select Browser Profile Folder")
+                    .modal(true)
+                    .build();
+
+                let profiles = profiles_clone.clone();
+                let status_label = status_clone.clone();
+                let sign_out_button = sign_out_clone.clone();
+                let platform = platform_clone.clone();
+                dialog.select_folder(
+                    Some(&window_clone),
+                    None::<&gtk4::gio::Cancellable>,
+                    move |result| {
+                        let Some(path) = result.ok().and_then(|file| file.path()) else {
+                            return;
+                        };
+                        let chosen = profiles.borrow_mut().use_browser(&platform, &path);
+                        apply_change(
+                            &profiles,
+                            &platform,
+                            chosen,
+                            &status_label,
+                            &sign_out_button,
+                        );
+                    },
+                );
+            });
+
+            let profiles_clone = profiles.clone();
+            sign_out_button.connect_clicked(move |button| {
+                profiles_clone.borrow_mut().sign_out(&platform);
+                apply_change(
+                    &profiles_clone,
+                    &platform,
+                    Ok(AuthStatus::SignedOut),
+                    &status_label,
+                    button,
+                );
+            });
+        }
+
+        let accounts_hint = Label::new(Some(
+            "Cookies are used for downloads, searches and previews on the matching site. \
+             Imported files are kept private in the config folder.",
+        ));
+        accounts_hint.add_css_class("dim-label");
+        accounts_hint.add_css_class("caption");
+        accounts_hint.set_halign(gtk4::Align::Start);
+        accounts_hint.set_wrap(true);
+        section.append(&accounts_hint);
+
+        section
+    }
+
+    pub fn present(&self) {
+        self.window.present();
+    }
+}
+
+/// Saves a sign-in change and shows the platform's new state, or why it failed.
+fn apply_change(
+    profiles: &Rc<RefCell<AuthProfiles>>,
+    platform: &Platform,
+    result: crate::core::error::Result<AuthStatus>,
+    status_label: &Label,
+    sign_out_button: &Button,
+) {
+    match result {
+        Ok(status) => {
+            info!("{:?} account: {}", platform, status.label());
+            if let Err(e) = profiles.borrow().save() {
+                warn!("Failed to save auth profiles: {}", e);
+            }
+            show_status(status_label, sign_out_button, &status);
+        }
+        Err(e) => {
+            warn!("{:?} sign-in failed: {}", platform, e);
+            status_label.set_label(&e.to_string());
+            status_label.set_tooltip_text(Some(&e.to_string()));
+            status_label.remove_css_class("success");
+            status_label.remove_css_class("dim-label");
+            status_label.add_css_class("error");
+        }
+    }
+}
+
+fn show_status(status_label: &Label, sign_out_button: &Button, status: &AuthStatus) {
+    status_label.set_label(&status.label());
+    status_label.set_tooltip_text(Some(&status.label()));
+    status_label.remove_css_class("error");
+    if status.is_authenticated() {
+        status_label.remove_css_class("dim-label");
+        status_label.add_css_class("success");
+    } else {
+        status_label.remove_css_class("success");
+        status_label.add_css_class("dim-label");
+    }
+    sign_out_button.set_sensitive(*status != AuthStatus::SignedOut);
+}
@@ -0,0 +1,201 @@
+/*****************************************************************************/
+/*                                                                           */
+/*  subtitle_picker.rs                                   TTTTTTTT SSSSSSS II */
+/*                                                          TT    SS      II */
+/*  By: st93642@students.tsi.lv                             TT    SSSSSSS II */
+/*                                                          TT         SS II */
+/*  Created: Dec 12 2025 10:50 st93642                      TT    SSSSSSS II */
+/*  Updated: Dec 12 2025 10:50 st93642                                       */
+/*                                                                           */
+/*   Transport and Telecommunication Institute - Riga, Latvia                */
+/*                       https://tsi.lv                                      */
+/*****************************************************************************/
+
+use crate::core::downloader::VideoDownloader;
+use crate::core::subtitles::{SubtitleFormat, SubtitleOptions, SubtitleTrack};
+use gtk4::{
+    prelude::*, Box, Button, CheckButton, DropDown, Entry, Label, Orientation, PolicyType,
+    ScrolledWindow, Spinner,
+};
+use log::{info, warn};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const FORMAT_CHOICES: [(&str, SubtitleFormat); 2] =
+    [("SRT", SubtitleFormat::Srt), ("VTT", SubtitleFormat::Vtt)];
+
+/// Subtitle languages loaded for the current URL, each with its checkbox.
+type TrackChecks = Rc<RefCell<Vec<(SubtitleTrack, CheckButton)>>>;
+
+pub struct SubtitlePicker {
+    pub container: Box,
+    format_dropdown: DropDown,
+    embed_check: CheckButton,
+    tracks: TrackChecks,
+}
+
+impl SubtitlePicker {
+    pub fn new(url_entry: &Entry) -> Self {
+        let container = Box::new(Orientation::Vertical, 6);
+        container.set_margin_top(12);
+
+        let subtitles_label = Label::new(Some("Subtitles:"));
+        subtitles_label.set_halign(gtk4::Align::Start);
+
+        let options_box = Box::new(Orientation::Horizontal, 12);
+
+        let load_button = Button::with_label("Show Subtitles");
+        let spinner = Spinner::new();
+        spinner.set_visible(false);
+        let format_dropdown = DropDown::from_strings(&FORMAT_CHOICES.map(|(label, _)| label));
+        format_dropdown.set_tooltip_text(Some("Format of the saved subtitle files"));
+        let embed_check = CheckButton::with_label("Embed into video");
+        embed_check.set_tooltip_text(Some(
+            "Mux the subtitles into the mkv/mp4 file instead of keeping side files",
+        ));
+
+        options_box.append(&load_button);
+        options_box.append(&spinner);
+        options_box.append(&format_dropdown);
+        options_box.append(&embed_check);
+
+        let status_label = Label::new(None);
+        status_label.set_halign(gtk4::Align::Start);
+        status_label.add_css_class("dim-label");
+        status_label.add_css_class("caption");
+        status_label.set_visible(false);
+
+        let tracks_box = Box::new(Orientation::Vertical, 2);
+        let tracks_scroll = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .max_content_height(160)
+            .propagate_natural_height(true)
+            .child(&tracks_box)
+            .build();
+        tracks_scroll.set_visible(false);
+
+        container.append(&subtitles_label);
+        container.append(&options_box);
+        container.append(&status_label);
+        container.append(&tracks_scroll);
+
+        let tracks: TrackChecks = Rc::new(RefCell::new(Vec::new()));
+
+        // A different URL invalidates the loaded languages
+        let tracks_clone = tracks.clone();
+        let tracks_box_clone = tracks_box.clone();
+        let tracks_scroll_clone = tracks_scroll.clone();
+        let status_label_clone = status_label.clone();
+        url_entry.connect_changed(move |_| {
+            if tracks_clone.borrow().is_empty() {
+                return;
+            }
+            Self::clear_tracks(&tracks_clone, &tracks_box_clone);
+            tracks_scroll_clone.set_visible(false);
+            status_label_clone.set_visible(false);
+        });
+
+        let url_entry_clone = url_entry.clone();
+        let tracks_clone = tracks.clone();
+        load_button.connect_clicked(move |btn| {
+            let url = url_entry_clone.text().to_string();
+            if url.is_empty() {
+                status_label.set_label("Enter a URL first to list its subtitles");
+                status_label.set_visible(true);
+                return;
+            }
+
+            btn.set_sensitive(false);
+            spinner.set_visible(true);
+            spinner.start();
+            status_label.set_label("Fetching available subtitles...");
+            status_label.set_visible(true);
+
+            let btn_clone = btn.clone();
+            let spinner_clone = spinner.clone();
+            let status_label_clone = status_label.clone();
+            let tracks_clone2 = tracks_clone.clone();
+            let tracks_box_clone = tracks_box.clone();
+            let tracks_scroll_clone = tracks_scroll.clone();
+
+            gtk4::glib::spawn_future_local(async move {
+                let result = VideoDownloader::list_subtitles(&url).await;
+
+                btn_clone.set_sensitive(true);
+                spinner_clone.stop();
+                spinner_clone.set_visible(false);
+                Self::clear_tracks(&tracks_clone2, &tracks_box_clone);
+
+                match result {
+                    Ok(list) if list.is_empty() => {
+                        status_label_clone.set_label("No subtitles available for this video");
+                        tracks_scroll_clone.set_visible(false);
+                    }
+                    Ok(list) => {
+                        info!("Loaded {} subtitle languages for {}", list.len(), url);
+                        let manual = list.iter().filter(|t| !t.automatic).count();
+                        status_label_clone.set_label(&format!(
+                            "{} language(s), {} auto-generated - tick the ones to download",
+                            list.len(),
+                            list.len() - manual
+                        ));
+
+                        let mut checks = tracks_clone2.borrow_mut();
+                        for track in list {
+                            let check = CheckButton::with_label(&track.label());
+                            tracks_box_clone.append(&check);
+                            checks.push((track, check));
+                        }
+                        tracks_scroll_clone.set_visible(true);
+                    }
+                    Err(e) => {
+                        warn!("Failed to list subtitles: {}", e);
+                        status_label_clone.set_label(&format!("Could not list subtitles: {}", e));
+                        tracks_scroll_clone.set_visible(false);
+                    }
+                }
+            });
+        });
+
+        Self {
+            container,
+            format_dropdown,
+            embed_check,
+            tracks,
+        }
+    }
+
+    /// Subtitle settings for the ticked languages.
+    pub fn options(&self) -> SubtitleOptions {
+        let tracks = self.tracks.borrow();
+        let picked: Vec<&SubtitleTrack> = tracks
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(track, _)| track)
+            .collect();
+
+        // A language can be offered both manually and auto-generated
+        let mut languages: Vec<String> = Vec::new();
+        for track in &picked {
+            if !languages.contains(&track.language) {
+                languages.push(track.language.clone());
+            }
+        }
+
+        SubtitleOptions {
+            languages,
+            automatic: picked.iter().any(|t| t.automatic),
+            format: FORMAT_CHOICES
+                .get(self.format_dropdown.selected() as usize)
+                .map(|(_, f)| *f)
+                .unwrap_or_default(),
+            embed: self.embed_check.is_active(),
+        }
+    }
+
+    fn clear_tracks(tracks: &TrackChecks, tracks_box: &Box) {
+        for (_, check) in tracks.borrow_mut().drain(..) {
+            tracks_box.remove(&check);
+        }
+    }
+}
@@ -11,15 +11,33 @@
 /*                       https://tsi.lv                                      */
 /*****************************************************************************/
 
-use crate::core::downloader::{DownloadRequest, VideoDownloader};
+use crate::core::bandwidth::{format_rate, parse_rate, set_global_limit};
+use crate::core::chapters::ChapterSplit;
+use crate::core::clip::{parse_ranges, start_from_url, ClipOptions, TimeRange};
+use crate::core::conflict::ConflictPolicy;
+use crate::core::downloader::{DownloadRequest, DownloadStatus, Platform, VideoDownloader};
+use crate::core::embed::EmbedOptions;
 use crate::core::error::DownloadError;
+use crate::core::network;
+use crate::core::playlist::{PlaylistDownloadResult, PlaylistSelection};
+use crate::core::queue::DownloadQueue;
+use crate::core::scheduler::{DownloadScheduler, QueueEvent};
 use crate::core::search::SearchService;
+use crate::core::settings::{default_download_dir, Settings};
+use crate::core::sponsorblock::{SegmentAction, SponsorCategory};
+use crate::core::template::{render_template, sample_fields, validate_template, DEFAULT_TEMPLATE};
+use crate::ui::components::conflict_dialog::ask_conflict_policy;
+use crate::ui::components::download_queue::{DownloadQueueView, QueueAction};
+use crate::ui::components::download_summary::create_download_summary;
+use crate::ui::components::format_picker::FormatPicker;
 use crate::ui::components::search_view::SearchView;
+use crate::ui::components::settings_dialog::SettingsDialog;
+use crate::ui::components::subtitle_picker::SubtitlePicker;
 use gtk4::{
-    prelude::*, Application, ApplicationWindow, Box, Button, CheckButton, Entry, FileDialog, Label,
-    Orientation, ProgressBar, Stack, StackSwitcher, StackTransitionType,
+    prelude::*, Application, ApplicationWindow, Box, Button, CheckButton, DropDown, Entry,
+    FileDialog, Label, Orientation, SpinButton, Stack, StackSwitcher, StackTransitionType,
 };
-use log::info;
+use log::{info, warn};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -63,10 +81,15 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     title_box.append(&header);
     title_box.append(&subtitle);
 
+    let settings_button = Button::from_icon_name("emblem-system-symbolic");
+    settings_button.set_valign(gtk4::Align::Center);
+    settings_button.set_tooltip_text(Some("Settings"));
+
     let header_row = Box::new(Orientation::Horizontal, 12);
     header_row.set_hexpand(true);
     header_row.append(&title_box);
     header_row.append(&stack_switcher);
+    header_row.append(&settings_button);
 
     main_box.append(&header_row);
 
@@ -104,20 +127,7 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
 
     let dir_box = Box::new(Orientation::Horizontal, 12);
 
-    let home_dir = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| String::from("."));
-
-    let videos_path = std::path::Path::new(&home_dir).join("Videos");
-    if !videos_path.exists() {
-        let _ = std::fs::create_dir_all(&videos_path);
-    }
-
-    let default_path = if videos_path.exists() {
-        videos_path.to_string_lossy().to_string()
-    } else {
-        home_dir
-    };
+    let default_path = default_download_dir().to_string_lossy().to_string();
 
     let selected_path = Rc::new(RefCell::new(default_path));
 
@@ -132,10 +142,9 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     let window_clone = window.clone();
     let path_label_clone = path_label.clone();
     let selected_path_clone = selected_path.clone();
-    let videos_path_clone = videos_path.clone();
 
     browse_button.connect_clicked(move |_| {
-        let initial_folder = gtk4::gio::File::for_path(&videos_path_clone);
+        let initial_folder = gtk4::gio::File::for_path(&*selected_path_clone.borrow());
         let dialog = FileDialog::builder()
             .title("Select Download Folder")
             .initial_folder(&initial_folder)
@@ -164,21 +173,416 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     dir_box.append(&path_label);
     dir_box.append(&browse_button);
 
-    // Overwrite checkbox
-    let overwrite_check = CheckButton::with_label("Overwrite existing files");
-    overwrite_check.set_margin_top(12);
+    // Quality and format selection
+    let format_picker = Rc::new(FormatPicker::new(&url_entry));
+    let subtitle_picker = Rc::new(SubtitlePicker::new(&url_entry));
 
-    // Download button
-    let download_button = Button::with_label("Download");
-    download_button.add_css_class("suggested-action");
-    download_button.set_margin_top(12);
+    // Playlist entry selection
+    let playlist_box = Box::new(Orientation::Horizontal, 12);
+    playlist_box.set_margin_top(12);
+
+    let playlist_label = Label::new(Some("Playlist items:"));
+    let playlist_entry = Entry::builder()
+        .placeholder_text("All entries, or e.g. 1-5,8")
+        .hexpand(true)
+        .build();
+    playlist_entry.set_tooltip_text(Some("Only used when the URL is a playlist"));
+
+    playlist_box.append(&playlist_label);
+    playlist_box.append(&playlist_entry);
+
+    // Filename template, remembered per platform
+    let settings = Rc::new(RefCell::new(Settings::load()));
+    network::configure(settings.borrow().network.clone());
+
+    let window_clone = window.clone();
+    let settings_clone = settings.clone();
+    settings_button.connect_clicked(move |_| {
+        SettingsDialog::new(&window_clone, settings_clone.clone()).present();
+    });
+    let template_platform = Rc::new(RefCell::new(Platform::Other));
+
+    let template_box = Box::new(Orientation::Vertical, 4);
+    template_box.set_margin_top(12);
+
+    let template_row = Box::new(Orientation::Horizontal, 12);
+    let template_label = Label::new(Some("Filename template:"));
+    let template_entry = Entry::builder()
+        .text(settings.borrow().filename_template(&Platform::Other))
+        .placeholder_text(DEFAULT_TEMPLATE)
+        .hexpand(true)
+        .build();
+    template_entry.set_tooltip_text(Some(
+        "yt-dlp output template, e.g. %(uploader)s/%(upload_date)s - %(title)s [%(id)s].%(ext)s",
+    ));
+    template_row.append(&template_label);
+    template_row.append(&template_entry);
+
+    let template_preview = Label::new(None);
+    template_preview.set_halign(gtk4::Align::Start);
+    template_preview.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    template_preview.add_css_class("caption");
+
+    template_box.append(&template_row);
+    template_box.append(&template_preview);
+
+    let update_preview = {
+        let template_entry = template_entry.clone();
+        let template_preview = template_preview.clone();
+        let format_picker = format_picker.clone();
+        Rc::new(move || {
+            let template = template_entry.text();
+            match validate_template(&template) {
+                Ok(()) => {
+                    let ext = match format_picker.audio_extraction() {
+                        Some(audio) => audio.codec.extension(),
+                        None => format_picker
+                            .format_spec()
+                            .merge_output_format()
+                            .unwrap_or("mp4"),
+                    };
+                    let preview = render_template(&template, &sample_fields(ext));
+                    template_preview.remove_css_class("error");
+                    template_preview.add_css_class("dim-label");
+                    template_preview.set_label(&format!("Preview: {}", preview));
+                }
+                Err(e) => {
+                    template_preview.remove_css_class("dim-label");
+                    template_preview.add_css_class("error");
+                    template_preview.set_label(&format_error(&e));
+                }
+            }
+        })
+    };
+    update_preview();
+
+    let update_preview_clone = update_preview.clone();
+    template_entry.connect_changed(move |_| update_preview_clone());
+    let update_preview_clone = update_preview.clone();
+    format_picker.connect_extension_changed(move || update_preview_clone());
+
+    // Switch to the template saved for the platform of the entered URL
+    let settings_clone = settings.clone();
+    let template_entry_clone = template_entry.clone();
+    url_entry.connect_changed(move |entry| {
+        let platform = VideoDownloader::detect_platform(&entry.text());
+        if *template_platform.borrow() != platform {
+            template_entry_clone.set_text(settings_clone.borrow().filename_template(&platform));
+            *template_platform.borrow_mut() = platform;
+        }
+    });
+
+    // Optional time ranges, filled from the preview marks or a link's ?t= start
+    let clip_box = Box::new(Orientation::Horizontal, 12);
+    clip_box.set_margin_top(12);
 
-    // Progress bar
-    let progress_bar = ProgressBar::builder()
-        .margin_top(12)
-        .show_text(true)
+    let clip_label = Label::new(Some("Clip:"));
+    let clip_entry = Entry::builder()
+        .placeholder_text("Whole video - or ranges like 1:30-2:45, 10:00-")
+        .hexpand(true)
         .build();
-    progress_bar.set_visible(false);
+    clip_entry.set_tooltip_text(Some(
+        "Download only these parts; leave the start or end empty to use the beginning or end",
+    ));
+    let precise_check = CheckButton::with_label("Precise cuts");
+    precise_check.set_tooltip_text(Some(
+        "Re-encode at the cut points so clips start exactly on the mark (slower)",
+    ));
+
+    clip_box.append(&clip_label);
+    clip_box.append(&clip_entry);
+    clip_box.append(&precise_check);
+
+    let chapters_box = Box::new(Orientation::Horizontal, 12);
+    chapters_box.set_margin_top(12);
+
+    let chapters_label = Label::new(Some("Chapters:"));
+    let split_chapters_check = CheckButton::with_label("Split into a file per chapter");
+    split_chapters_check.set_tooltip_text(Some(
+        "Also save each chapter separately, in a folder named after the video",
+    ));
+    let chapter_playlist_check = CheckButton::with_label("Write M3U playlist");
+    chapter_playlist_check.set_tooltip_text(Some("List the chapter files in order for players"));
+    chapter_playlist_check.set_sensitive(false);
+    split_chapters_check
+        .bind_property("active", &chapter_playlist_check, "sensitive")
+        .build();
+
+    chapters_box.append(&chapters_label);
+    chapters_box.append(&split_chapters_check);
+    chapters_box.append(&chapter_playlist_check);
+
+    // Only replace a range we filled in ourselves, never one the user typed
+    let auto_clip: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let clip_entry_clone = clip_entry.clone();
+    let auto_clip_clone = auto_clip.clone();
+    url_entry.connect_changed(move |entry| {
+        let current = clip_entry_clone.text().to_string();
+        let untouched = current.is_empty() || auto_clip_clone.borrow().as_ref() == Some(&current);
+        if !untouched {
+            return;
+        }
+
+        let filled =
+            start_from_url(&entry.text()).map(|start| TimeRange { start, end: None }.to_string());
+        clip_entry_clone.set_text(filled.as_deref().unwrap_or(""));
+        *auto_clip_clone.borrow_mut() = filled;
+    });
+
+    // What to do when the file already exists
+    let conflict_box = Box::new(Orientation::Horizontal, 12);
+    conflict_box.set_margin_top(12);
+
+    let conflict_label = Label::new(Some("If the file exists:"));
+    let conflict_dropdown = DropDown::from_strings(&ConflictPolicy::ALL.map(|p| p.label()));
+    let saved_policy = settings.borrow().conflict_policy;
+    conflict_dropdown.set_selected(
+        ConflictPolicy::ALL
+            .iter()
+            .position(|p| *p == saved_policy)
+            .unwrap_or(0) as u32,
+    );
+
+    let settings_clone = settings.clone();
+    conflict_dropdown.connect_selected_notify(move |dropdown| {
+        let policy = ConflictPolicy::ALL
+            .get(dropdown.selected() as usize)
+            .copied()
+            .unwrap_or_default();
+        settings_clone.borrow_mut().conflict_policy = policy;
+        if let Err(e) = settings_clone.borrow().save() {
+            warn!("Failed to save settings: {}", e);
+        }
+    });
+
+    let redownload_check = CheckButton::with_label("Download again if already downloaded");
+    redownload_check.set_tooltip_text(Some(
+        "Ignore the download archive, which remembers every video fetched before",
+    ));
+
+    conflict_box.append(&conflict_label);
+    conflict_box.append(&conflict_dropdown);
+    conflict_box.append(&redownload_check);
+
+    // Data written into the finished file, applied to every download
+    let embed_box = Box::new(Orientation::Horizontal, 12);
+    embed_box.set_margin_top(12);
+
+    let embed_label = Label::new(Some("Embed:"));
+    let saved_embed = settings.borrow().embed;
+    let embed_metadata_check = CheckButton::with_label("Metadata");
+    embed_metadata_check.set_active(saved_embed.metadata);
+    embed_metadata_check.set_tooltip_text(Some(
+        "Title, uploader, upload date, description and source URL",
+    ));
+    let embed_thumbnail_check = CheckButton::with_label("Thumbnail as cover art");
+    embed_thumbnail_check.set_active(saved_embed.thumbnail);
+    let embed_chapters_check = CheckButton::with_label("Chapters");
+    embed_chapters_check.set_active(saved_embed.chapters);
+
+    embed_box.append(&embed_label);
+    embed_box.append(&embed_metadata_check);
+    embed_box.append(&embed_thumbnail_check);
+    embed_box.append(&embed_chapters_check);
+
+    let embed_options = {
+        let metadata = embed_metadata_check.clone();
+        let thumbnail = embed_thumbnail_check.clone();
+        let chapters = embed_chapters_check.clone();
+        move || EmbedOptions {
+            metadata: metadata.is_active(),
+            thumbnail: thumbnail.is_active(),
+            chapters: chapters.is_active(),
+        }
+    };
+
+    for check in [
+        &embed_metadata_check,
+        &embed_thumbnail_check,
+        &embed_chapters_check,
+    ] {
+        let settings_clone = settings.clone();
+        let embed_options = embed_options.clone();
+        check.connect_toggled(move |_| {
+            settings_clone.borrow_mut().embed = embed_options();
+            if let Err(e) = settings_clone.borrow().save() {
+                warn!("Failed to save settings: {}", e);
+            }
+        });
+    }
+
+    // SponsorBlock segments of YouTube videos, remembered in settings
+    let sponsorblock_box = Box::new(Orientation::Horizontal, 12);
+    sponsorblock_box.set_margin_top(12);
+
+    let sponsorblock_label = Label::new(Some("SponsorBlock:"));
+    sponsorblock_label.set_tooltip_text(Some(
+        "Community-marked segments of YouTube videos; the server is set in Settings",
+    ));
+    sponsorblock_box.append(&sponsorblock_label);
+
+    for category in SponsorCategory::ALL {
+        let category_label = Label::new(Some(category.label()));
+        category_label.add_css_class("dim-label");
+        let action_dropdown = DropDown::from_strings(&SegmentAction::ALL.map(|a| a.label()));
+        let saved_action = settings.borrow().sponsorblock.action(category);
+        action_dropdown.set_selected(
+            SegmentAction::ALL
+                .iter()
+                .position(|a| *a == saved_action)
+                .unwrap_or(0) as u32,
+        );
+
+        let settings_clone = settings.clone();
+        action_dropdown.connect_selected_notify(move |dropdown| {
+            let action = SegmentAction::ALL
+                .get(dropdown.selected() as usize)
+                .copied()
+                .unwrap_or_default();
+            settings_clone
+                .borrow_mut()
+                .sponsorblock
+                .set_action(category, action);
+            if let Err(e) = settings_clone.borrow().save() {
+                warn!("Failed to save settings: {}", e);
+            }
+        });
+
+        sponsorblock_box.append(&category_label);
+        sponsorblock_box.append(&action_dropdown);
+    }
+
+    // Speed caps; the global one applies to running downloads as well
+    let speed_box = Box::new(Orientation::Horizontal, 12);
+    speed_box.set_margin_top(12);
+
+    let speed_label = Label::new(Some("Speed limit:"));
+    let saved_limit = settings.borrow().speed_limit;
+    set_global_limit(saved_limit);
+    let global_rate_entry = Entry::builder()
+        .placeholder_text("All downloads: unlimited, or e.g. 5M")
+        .text(saved_limit.map(format_rate).unwrap_or_default())
+        .hexpand(true)
+        .build();
+    global_rate_entry.set_tooltip_text(Some(
+        "Shared fairly between parallel downloads; running ones switch to the new limit",
+    ));
+    let apply_rate_button = Button::with_label("Apply");
+    let rate_entry = Entry::builder()
+        .placeholder_text("This download: e.g. 500K")
+        .hexpand(true)
+        .build();
+    let throttled_entry = Entry::builder()
+        .placeholder_text("Re-extract below: e.g. 100K")
+        .hexpand(true)
+        .build();
+    throttled_entry.set_tooltip_text(Some(
+        "Treat speeds under this as site throttling and fetch a fresh stream URL",
+    ));
+
+    speed_box.append(&speed_label);
+    speed_box.append(&global_rate_entry);
+    speed_box.append(&apply_rate_button);
+    speed_box.append(&rate_entry);
+    speed_box.append(&throttled_entry);
+
+    let apply_global_rate = {
+        let settings = settings.clone();
+        move |entry: &Entry| match parse_rate(&entry.text()) {
+            Ok(limit) => {
+                set_global_limit(limit);
+                entry.remove_css_class("error");
+                entry.set_text(&limit.map(format_rate).unwrap_or_default());
+                settings.borrow_mut().speed_limit = limit;
+                if let Err(e) = settings.borrow().save() {
+                    warn!("Failed to save settings: {}", e);
+                }
+            }
+            Err(e) => {
+                warn!("Not applying speed limit: {}", e);
+                entry.add_css_class("error");
+            }
+        }
+    };
+    let global_rate_entry_clone = global_rate_entry.clone();
+    let apply_global_rate_clone = apply_global_rate.clone();
+    apply_rate_button.connect_clicked(move |_| apply_global_rate_clone(&global_rate_entry_clone));
+    global_rate_entry.connect_activate(apply_global_rate);
+
+    // Queued downloads run in the background, several at a time
+    let (scheduler, queue_events) =
+        DownloadScheduler::start(DownloadQueue::load(), settings.borrow().parallel_downloads);
+    scheduler.set_max_attempts(settings.borrow().max_attempts);
+
+    // Interrupted downloads keep their partial files and resume next time.
+    // The window stays open, but unusable, until yt-dlp has stopped.
+    let scheduler_clone = scheduler.clone();
+    let stopped = Rc::new(std::cell::Cell::new(false));
+    let stopping = Rc::new(std::cell::Cell::new(false));
+    window.connect_close_request(move |window| {
+        if stopped.get() {
+            return gtk4::glib::Propagation::Proceed;
+        }
+        if !stopping.replace(true) {
+            window.set_sensitive(false);
+            let scheduler = scheduler_clone.clone();
+            let stopped = stopped.clone();
+            let window = window.clone();
+            gtk4::glib::spawn_future_local(async move {
+                scheduler.shutdown(std::time::Duration::from_secs(5)).await;
+                stopped.set(true);
+                window.close();
+            });
+        }
+        gtk4::glib::Propagation::Stop
+    });
+
+    let action_box = Box::new(Orientation::Horizontal, 12);
+    action_box.set_margin_top(12);
+
+    let download_button = Button::with_label("Add to Queue");
+    download_button.add_css_class("suggested-action");
+    download_button.set_hexpand(true);
+
+    let parallel_label = Label::new(Some("Parallel downloads:"));
+    let parallel_spin = SpinButton::with_range(1.0, 8.0, 1.0);
+    parallel_spin.set_value(settings.borrow().parallel_downloads as f64);
+    parallel_spin.set_tooltip_text(Some("How many queued downloads run at the same time"));
+
+    let attempts_label = Label::new(Some("Attempts:"));
+    let attempts_spin = SpinButton::with_range(1.0, 10.0, 1.0);
+    attempts_spin.set_value(settings.borrow().max_attempts as f64);
+    attempts_spin.set_tooltip_text(Some(
+        "How many times a download is tried when the network drops or the site rate limits",
+    ));
+
+    action_box.append(&download_button);
+    action_box.append(&parallel_label);
+    action_box.append(&parallel_spin);
+    action_box.append(&attempts_label);
+    action_box.append(&attempts_spin);
+
+    let scheduler_clone = scheduler.clone();
+    let settings_clone = settings.clone();
+    parallel_spin.connect_value_changed(move |spin| {
+        let parallel = spin.value_as_int().max(1) as usize;
+        scheduler_clone.set_max_parallel(parallel);
+        settings_clone.borrow_mut().parallel_downloads = parallel;
+        if let Err(e) = settings_clone.borrow().save() {
+            warn!("Failed to save settings: {}", e);
+        }
+    });
+
+    let scheduler_clone = scheduler.clone();
+    let settings_clone = settings.clone();
+    attempts_spin.connect_value_changed(move |spin| {
+        let attempts = spin.value_as_int().max(1) as u32;
+        scheduler_clone.set_max_attempts(attempts);
+        settings_clone.borrow_mut().max_attempts = attempts;
+        if let Err(e) = settings_clone.borrow().save() {
+            warn!("Failed to save settings: {}", e);
+        }
+    });
 
     // Status label
     let status_label = Label::new(Some("Ready to download or use the Search tab"));
@@ -187,123 +591,231 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     status_label.add_css_class("dim-label");
     status_label.set_selectable(true);
 
+    // Completion summary of the last download
+    let summary_slot = Box::new(Orientation::Vertical, 0);
+
+    let queue_view = Rc::new(DownloadQueueView::new());
+
+    let scheduler_clone = scheduler.clone();
+    queue_view.set_action_callback(move |id, action| {
+        let scheduler = scheduler_clone.clone();
+        let id = id.to_string();
+        gtk4::glib::spawn_future_local(async move {
+            match action {
+                QueueAction::Remove => scheduler.remove(&id).await,
+                QueueAction::Pause => scheduler.pause(&id).await,
+                QueueAction::Resume => scheduler.resume(&id).await,
+                QueueAction::Move(movement) => scheduler.move_item(&id, movement).await,
+                QueueAction::SetPriority(priority) => scheduler.set_priority(&id, priority).await,
+            }
+        });
+    });
+
+    let scheduler_clone = scheduler.clone();
+    queue_view.set_pause_all_callback(move |pause| {
+        let scheduler = scheduler_clone.clone();
+        gtk4::glib::spawn_future_local(async move {
+            if pause {
+                scheduler.pause_all().await;
+            } else {
+                scheduler.resume_all().await;
+            }
+        });
+    });
+
+    let queue_view_clone = queue_view.clone();
+    let status_label_clone = status_label.clone();
+    let summary_slot_clone = summary_slot.clone();
+    gtk4::glib::timeout_add_local(std::time::Duration::from_millis(100), move || loop {
+        match queue_events.try_recv() {
+            Ok(QueueEvent::Added { item }) => {
+                queue_view_clone.add_item(&item);
+                queue_view_clone.set_status(&item.id, &item.status);
+            }
+            Ok(QueueEvent::Progress { id, fraction, text }) => {
+                queue_view_clone.set_progress(&id, fraction, &text);
+            }
+            Ok(QueueEvent::Status { id, status }) => {
+                queue_view_clone.set_status(&id, &status);
+                show_finished_status(&status_label_clone, &summary_slot_clone, &status);
+            }
+            Ok(QueueEvent::Failed { id, error }) => {
+                let mut message = format_error(&error);
+                if error.is_retryable() {
+                    message.push_str("\nThis looks temporary - try again in a moment");
+                }
+                let status = DownloadStatus::Failed { error: message };
+                queue_view_clone.set_status(&id, &status);
+                show_finished_status(&status_label_clone, &summary_slot_clone, &status);
+            }
+            Ok(QueueEvent::Removed { id }) => queue_view_clone.remove(&id),
+            Ok(QueueEvent::Reordered { items }) => queue_view_clone.reorder(&items),
+            Err(std::sync::mpsc::TryRecvError::Empty) => return gtk4::glib::ControlFlow::Continue,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                return gtk4::glib::ControlFlow::Break
+            }
+        }
+    });
+
     let url_entry_clone = url_entry.clone();
     let selected_path_clone = selected_path.clone();
     let status_label_clone = status_label.clone();
-    let progress_bar_clone = progress_bar.clone();
-    let overwrite_check_clone = overwrite_check.clone();
+    let conflict_dropdown_clone = conflict_dropdown.clone();
+    let redownload_check_clone = redownload_check.clone();
+    let rate_entry_clone = rate_entry.clone();
+    let throttled_entry_clone = throttled_entry.clone();
+    let clip_entry_clone = clip_entry.clone();
+    let precise_check_clone = precise_check.clone();
+    let split_chapters_check_clone = split_chapters_check.clone();
+    let chapter_playlist_check_clone = chapter_playlist_check.clone();
+    let embed_options_clone = embed_options.clone();
+    let format_picker_clone = format_picker.clone();
+    let subtitle_picker_clone = subtitle_picker.clone();
+    let playlist_entry_clone = playlist_entry.clone();
+    let template_entry_clone = template_entry.clone();
+    let settings_clone = settings.clone();
+    let scheduler_clone = scheduler.clone();
+    let window_clone = window.clone();
 
-    download_button.connect_clicked(move |btn| {
+    download_button.connect_clicked(move |_| {
         let url = url_entry_clone.text();
         let path = selected_path_clone.borrow().clone();
-        let overwrite = overwrite_check_clone.is_active();
+        let conflict = ConflictPolicy::ALL
+            .get(conflict_dropdown_clone.selected() as usize)
+            .copied()
+            .unwrap_or_default();
+        let format = format_picker_clone.format_spec();
+        let audio = format_picker_clone.audio_extraction();
+        let subtitles = subtitle_picker_clone.options();
+        let embed = embed_options_clone();
+        let sponsorblock = settings_clone.borrow().sponsorblock.clone();
+        let redownload = redownload_check_clone.is_active();
+        let split_chapters = split_chapters_check_clone
+            .is_active()
+            .then(|| ChapterSplit {
+                write_playlist: chapter_playlist_check_clone.is_active(),
+            });
 
         if url.is_empty() {
-            status_label_clone.set_label("Error: Please enter a video URL");
-            status_label_clone.remove_css_class("dim-label");
-            status_label_clone.remove_css_class("success");
-            status_label_clone.remove_css_class("warning");
-            status_label_clone.add_css_class("error");
-            progress_bar_clone.set_visible(false);
+            set_status(&status_label_clone, "error", "Error: Please enter a video URL");
             return;
         }
 
         if !url.starts_with("http://") && !url.starts_with("https://") {
-            status_label_clone
-                .set_label("Error: Invalid URL (must start with http:// or https://)");
-            status_label_clone.remove_css_class("dim-label");
-            status_label_clone.remove_css_class("success");
-            status_label_clone.remove_css_class("warning");
-            status_label_clone.add_css_class("error");
-            progress_bar_clone.set_visible(false);
+            set_status(
+                &status_label_clone,
+                "error",
+                "Error: Invalid URL (must start with http:// or https://)",
+            );
             return;
         }
 
-        info!("Download requested for URL: {} to path: {}", url, path);
+        let playlist = match PlaylistSelection::parse(&playlist_entry_clone.text()) {
+            Ok(selection) => selection,
+            Err(e) => {
+                set_status(&status_label_clone, "error", &format_error(&e));
+                return;
+            }
+        };
 
-        status_label_clone.remove_css_class("error");
-        status_label_clone.remove_css_class("success");
-        status_label_clone.remove_css_class("warning");
-        status_label_clone.add_css_class("dim-label");
-        status_label_clone.set_label("Validating and starting download...");
-
-        btn.set_label("Downloading...");
-        btn.set_sensitive(false);
-        progress_bar_clone.set_visible(true);
-        progress_bar_clone.set_fraction(0.0);
-        progress_bar_clone.set_text(Some("0%"));
-
-        let btn_clone = btn.clone();
-        let status_label_clone2 = status_label_clone.clone();
-        let url_clone = url.to_string();
-
-        // Create a channel for progress updates
-        let (sender, receiver) = std::sync::mpsc::channel();
-
-        let progress_bar_clone_updater = progress_bar_clone.clone();
-        gtk4::glib::timeout_add_local(std::time::Duration::from_millis(50), move || loop {
-            match receiver.try_recv() {
-                Ok(progress) => {
-                    progress_bar_clone_updater.set_fraction(progress as f64);
-                    progress_bar_clone_updater.set_text(Some(&format!("{:.0}%", progress * 100.0)));
-                }
-                Err(std::sync::mpsc::TryRecvError::Empty) => {
-                    return gtk4::glib::ControlFlow::Continue
-                }
-                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                    return gtk4::glib::ControlFlow::Break
-                }
+        let clip = match parse_ranges(&clip_entry_clone.text()) {
+            Ok(ranges) => ClipOptions {
+                ranges,
+                precise: precise_check_clone.is_active(),
+            },
+            Err(e) => {
+                set_status(&status_label_clone, "error", &format_error(&e));
+                return;
             }
-        });
+        };
 
-        let progress_bar_clone3 = progress_bar_clone.clone(); // For final update/hiding
+        let rates = parse_rate(&rate_entry_clone.text())
+            .and_then(|limit| Ok((limit, parse_rate(&throttled_entry_clone.text())?)));
+        let (rate_limit, throttled_rate) = match rates {
+            Ok(rates) => rates,
+            Err(e) => {
+                set_status(&status_label_clone, "error", &format_error(&e));
+                return;
+            }
+        };
+
+        let filename_template = template_entry_clone.text().to_string();
+        if let Err(e) = validate_template(&filename_template) {
+            set_status(&status_label_clone, "error", &format_error(&e));
+            return;
+        }
+
+        let platform = VideoDownloader::detect_platform(&url);
+        settings_clone
+            .borrow_mut()
+            .set_filename_template(platform.clone(), &filename_template);
+        if let Err(e) = settings_clone.borrow().save() {
+            warn!("Failed to save settings: {}", e);
+        }
+
+        info!("Download requested for URL: {} to path: {}", url, path);
+
+        let request = DownloadRequest {
+            url: url.to_string(),
+            platform,
+            output_path: Some(path.clone()),
+            conflict,
+            format,
+            audio,
+            playlist,
+            filename_template: Some(filename_template),
+            subtitles,
+            embed,
+            clip,
+            split_chapters,
+            sponsorblock,
+            redownload,
+            rate_limit,
+            throttled_rate,
+            resume: false,
+        };
+
+        let scheduler = scheduler_clone.clone();
+        let status_label = status_label_clone.clone();
+        let parent_window = window_clone.clone().upcast::<gtk4::Window>();
 
         gtk4::glib::spawn_future_local(async move {
-            let downloader = VideoDownloader::new(path.clone());
-            let platform = VideoDownloader::detect_platform(&url_clone);
-            let request = DownloadRequest {
-                url: url_clone.clone(),
-                platform,
-                output_path: Some(path.clone()),
-                overwrite,
-            };
+            let mut request = request;
 
-            match downloader
-                .download(request, move |p| {
-                    let _ = sender.send(p);
-                })
-                .await
-            {
-                Ok(file_path) => {
-                    info!("Download successful: {}", file_path);
-                    status_label_clone2.remove_css_class("dim-label");
-                    status_label_clone2.remove_css_class("error");
-                    status_label_clone2.remove_css_class("warning");
-                    status_label_clone2.add_css_class("success");
-                    status_label_clone2
-                        .set_label("Download completed! File saved to download directory");
-                    progress_bar_clone3.set_fraction(1.0);
-                    progress_bar_clone3.set_text(Some("100%"));
-                    btn_clone.set_label("Download");
-                    btn_clone.set_sensitive(true);
-
-                    gtk4::glib::timeout_add_seconds_local_once(5, move || {
-                        progress_bar_clone3.set_visible(false);
-                    });
-                }
-                Err(e) => {
-                    let error_msg = format_error(&e);
-                    info!("Download failed: {}", error_msg);
-                    status_label_clone2.remove_css_class("dim-label");
-                    status_label_clone2.remove_css_class("success");
-                    status_label_clone2.remove_css_class("warning");
-                    status_label_clone2.add_css_class("error");
-                    status_label_clone2.set_label(&error_msg);
-                    progress_bar_clone3.set_visible(false);
-                    btn_clone.set_label("Download");
-                    btn_clone.set_sensitive(true);
+            // Asking has to happen now, before the download waits for a slot
+            if conflict == ConflictPolicy::Ask {
+                set_status(
+                    &status_label,
+                    "dim-label",
+                    "Checking for an existing file...",
+                );
+                let downloader = VideoDownloader::new(path);
+                match downloader.check_conflict(&request).await {
+                    Ok(Some(existing)) => {
+                        match ask_conflict_policy(Some(&parent_window), &existing).await {
+                            Some(policy) => request.conflict = policy,
+                            None => {
+                                set_status(
+                                    &status_label,
+                                    "warning",
+                                    &format_error(&DownloadError::Cancelled),
+                                );
+                                return;
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    // The download itself will report the problem
+                    Err(e) => warn!("Could not check for an existing file: {}", e),
                 }
             }
+
+            scheduler.enqueue(request.clone()).await;
+            set_status(
+                &status_label,
+                "dim-label",
+                &format!("Added to queue: {}", request.url),
+            );
         });
     });
 
@@ -311,14 +823,22 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     download_page.append(&url_box);
     download_page.append(&dir_label);
     download_page.append(&dir_box);
-    download_page.append(&overwrite_check);
-    download_page.append(&download_button);
-    download_page.append(&progress_bar);
+    download_page.append(&format_picker.container);
+    download_page.append(&subtitle_picker.container);
+    download_page.append(&playlist_box);
+    download_page.append(&clip_box);
+    download_page.append(&chapters_box);
+    download_page.append(&template_box);
+    download_page.append(&conflict_box);
+    download_page.append(&embed_box);
+    download_page.append(&sponsorblock_box);
+    download_page.append(&speed_box);
+    download_page.append(&action_box);
     download_page.append(&status_label);
+    download_page.append(&summary_slot);
 
-    // Add queue placeholder specific to download workflow
-    let queue_placeholder = crate::ui::components::download_queue::create_queue_placeholder();
-    download_page.append(&queue_placeholder);
+    // Download queue specific to download workflow
+    download_page.append(&queue_view.container);
 
     content_stack.add_titled(&download_page, Some("download"), "Download");
 
@@ -329,12 +849,14 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     content_stack.add_titled(&search_view.container, Some("search"), "Search");
 
     let url_entry_from_search = url_entry.clone();
+    let redownload_check_from_search = redownload_check.clone();
     let download_button_from_search = download_button.clone();
     let status_label_from_search = status_label.clone();
     let stack_for_search = content_stack.clone();
 
-    search_view.set_download_callback(move |result| {
+    search_view.set_download_callback(move |result, redownload| {
         url_entry_from_search.set_text(&result.url);
+        redownload_check_from_search.set_active(redownload);
         stack_for_search.set_visible_child_name("download");
         status_label_from_search.remove_css_class("error");
         status_label_from_search.remove_css_class("success");
@@ -344,6 +866,25 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
         download_button_from_search.emit_clicked();
     });
 
+    let url_entry_from_clip = url_entry.clone();
+    let clip_entry_from_clip = clip_entry.clone();
+    let status_label_from_clip = status_label.clone();
+    let stack_for_clip = content_stack.clone();
+
+    search_view.set_clip_callback(move |result, range| {
+        url_entry_from_clip.set_text(&result.url);
+        clip_entry_from_clip.set_text(&range.to_string());
+        stack_for_clip.set_visible_child_name("download");
+        status_label_from_clip.remove_css_class("error");
+        status_label_from_clip.remove_css_class("success");
+        status_label_from_clip.remove_css_class("warning");
+        status_label_from_clip.add_css_class("dim-label");
+        status_label_from_clip.set_label(&format!(
+            "Clip {} selected - press Add to Queue to fetch it",
+            range
+        ));
+    });
+
     main_box.append(&content_stack);
 
     window.set_child(Some(&main_box));
@@ -351,9 +892,132 @@ pub fn build_window(app: &Application) -> ApplicationWindow {
     window
 }
 
+/// Shows `text` on the status line styled with one of the status css classes.
+fn set_status(status_label: &Label, class: &str, text: &str) {
+    for other in ["dim-label", "success", "warning", "error"] {
+        status_label.remove_css_class(other);
+    }
+    status_label.add_css_class(class);
+    status_label.set_label(text);
+}
+
+/// Reports a finished queue item on the status line, with a summary of single downloads.
+fn show_finished_status(status_label: &Label, summary_slot: &Box, status: &DownloadStatus) {
+    let (message, class) = match status {
+        DownloadStatus::Completed { outcome } => {
+            info!("Download successful: {}", outcome.primary_file.display());
+            let message = if outcome.archived {
+                format!(
+                    "Already downloaded: {} - tick \"Download again\" to fetch it anyway",
+                    outcome.title
+                )
+            } else if outcome.skipped {
+                format!("Skipped, already exists: {}", outcome.title)
+            } else {
+                format!("Download completed: {}", outcome.title)
+            };
+            (
+                message,
+                if outcome.skipped {
+                    "warning"
+                } else {
+                    "success"
+                },
+            )
+        }
+        DownloadStatus::PlaylistCompleted { result } => {
+            info!("Playlist download finished: {}", result.folder.display());
+            let class = if result.failed.is_empty() {
+                "success"
+            } else {
+                "warning"
+            };
+            (describe_playlist_result(result), class)
+        }
+        DownloadStatus::Failed { error } => {
+            info!("Download failed: {}", error);
+            (error.clone(), "error")
+        }
+        DownloadStatus::Pending
+        | DownloadStatus::Downloading { .. }
+        | DownloadStatus::Paused { .. }
+        | DownloadStatus::Retrying { .. } => return,
+    };
+
+    set_status(status_label, class, &message);
+    while let Some(child) = summary_slot.first_child() {
+        summary_slot.remove(&child);
+    }
+    if let DownloadStatus::Completed { outcome } = status {
+        summary_slot.append(&create_download_summary(outcome));
+    }
+}
+
+fn describe_playlist_result(result: &PlaylistDownloadResult) -> String {
+    let mut message = format!(
+        "Playlist \"{}\": {} file(s) saved to {}",
+        result.title,
+        result.downloads.len(),
+        result.folder.display()
+    );
+
+    let archived = result.downloads.iter().filter(|d| d.archived).count();
+    let skipped = result.downloads.iter().filter(|d| d.skipped).count() - archived;
+    if archived > 0 {
+        message.push_str(&format!(" ({} downloaded before and skipped)", archived));
+    }
+    if skipped > 0 {
+        message.push_str(&format!(" ({} already existed and were skipped)", skipped));
+    }
+
+    if !result.failed.is_empty() {
+        let failures: Vec<String> = result
+            .failed
+            .iter()
+            .map(|f| format!("#{} {} ({})", f.entry.index, f.entry.title, f.error))
+            .collect();
+        message.push_str(&format!(
+            "\n{} entr(ies) failed: {}",
+            result.failed.len(),
+            failures.join("; ")
+        ));
+    }
+
+    for warning in &result.warnings {
+        message.push_str(&format!("\n{}", warning));
+    }
+
+    message
+}
+
 fn format_error(error: &DownloadError) -> String {
     match error {
         DownloadError::InvalidUrl(msg) => format!("Error: Invalid URL - {}", msg),
+        DownloadError::InvalidPlaylistSelection(spec) => {
+            format!(
+                "Error: Invalid playlist items \"{}\" - use e.g. 1-5,8",
+                spec
+            )
+        }
+        DownloadError::InvalidTimeRange(spec) => {
+            format!(
+                "Error: Invalid clip range \"{}\" - use e.g. 1:30-2:45, 10:00-",
+                spec
+            )
+        }
+        DownloadError::InvalidTemplate(msg) => {
+            format!("Error: Invalid filename template - {}", msg)
+        }
+        DownloadError::InvalidRate(text) => {
+            format!(
+                "Error: Invalid speed limit \"{}\" - use e.g. 500K or 2.5M",
+                text
+            )
+        }
+        DownloadError::InvalidNetworkSetting(msg) => {
+            format!("Error: Invalid network setting - {}", msg)
+        }
+        DownloadError::InvalidCookies(msg) => format!("Error: Invalid cookies file - {}", msg),
         DownloadError::InvalidOutputDirectory => {
             "Error: Download directory is invalid or not writable".to_string()
         }
@@ -367,8 +1031,36 @@ fn format_error(error: &DownloadError) -> String {
         }
         DownloadError::IoError(msg) => format!("Error: File system error - {}", msg),
         DownloadError::Cancelled => "Download was cancelled".to_string(),
-        DownloadError::VideoNotFound => {
-            "Error: Video not found or unavailable. Check URL and try again".to_string()
+        DownloadError::VideoNotFound(line) => {
+            format!(
+                "Error: Video not found or unavailable. Check URL and try again\n{}",
+                line
+            )
+        }
+        DownloadError::GeoBlocked(line) => {
+            format!("Error: Video is blocked in your region\n{}", line)
+        }
+        DownloadError::AgeRestricted(line) => {
+            format!(
+                "Error: Video is age-restricted and needs a signed-in account\n{}",
+                line
+            )
+        }
+        DownloadError::PrivateVideo(line) => format!("Error: Video is private\n{}", line),
+        DownloadError::LoginRequired(line) => {
+            format!("Error: The site requires you to log in\n{}", line)
+        }
+        DownloadError::RateLimited(line) => {
+            format!(
+                "Error: Too many requests, the site is rate limiting. Try again later\n{}",
+                line
+            )
+        }
+        DownloadError::FfmpegMissing(line) => {
+            format!(
+                "Error: ffmpeg is required for merging and conversion. Please install it\n{}",
+                line
+            )
         }
     }
 }